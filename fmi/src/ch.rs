@@ -0,0 +1,99 @@
+use contraction::contract;
+use {MapData, Record, Storage, TravelType};
+
+/// `middle` of edges that are no shortcuts
+pub const NO_MIDDLE: u32 = u32::MAX;
+
+/// edge of a contraction hierarchy, always leads to a node with a higher rank
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChEdge {
+    /// node on the other end of the edge
+    pub neighbor: u32,
+    /// contracted node a shortcut skips, NO_MIDDLE for original ways
+    pub middle: u32,
+    /// in km or h, see `ContractionHierarchy::by_distance`
+    pub weight: f64,
+}
+
+unsafe impl Record for ChEdge {
+    const SIZE: usize = 16;
+
+    fn write_le(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.neighbor.to_le_bytes());
+        buf.extend_from_slice(&self.middle.to_le_bytes());
+        buf.extend_from_slice(&self.weight.to_bits().to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        let mut weight = [0u8; 8];
+        weight.copy_from_slice(&bytes[8..16]);
+        return ChEdge {
+            neighbor: u32::read_le(&bytes[0..]),
+            middle: u32::read_le(&bytes[4..]),
+            weight: f64::from_bits(u64::from_le_bytes(weight)),
+        };
+    }
+}
+
+/// contraction hierarchy of the ways one travel type may use, weighted by distance or travel time
+#[derive(Clone, Debug)]
+pub struct ContractionHierarchy {
+    pub travel_type: u32,
+    pub by_distance: bool,
+    /// position of each node in the contraction order
+    pub ranks: Storage<u32>,
+    /// edges from node i to higher ranked nodes are up[up_offset[i]..up_offset[i + 1]]
+    pub up: Storage<ChEdge>,
+    pub up_offset: Storage<u32>,
+    /// edges from higher ranked nodes to node i are down[down_offset[i]..down_offset[i + 1]]
+    pub down: Storage<ChEdge>,
+    pub down_offset: Storage<u32>,
+}
+
+impl ContractionHierarchy {
+    /// contracts the ways the travel type may use, weighted by distance in km or by travel time in h
+    pub fn new(map_data: &MapData, travel_type: TravelType, by_distance: bool) -> ContractionHierarchy {
+        return contract(map_data, travel_type, by_distance);
+    }
+
+    /// size of all arrays in bytes
+    pub fn byte_size(&self) -> usize {
+        return self.ranks.byte_size()
+            + self.up.byte_size()
+            + self.up_offset.byte_size()
+            + self.down.byte_size()
+            + self.down_offset.byte_size();
+    }
+
+    /// returns the cheapest edge from source to target, one of them has to be ranked higher than the other
+    pub fn get_edge(&self, source: usize, target: usize) -> Option<ChEdge> {
+        let (edges, neighbor) = if self.ranks[source] < self.ranks[target] {
+            (&self.up[self.up_offset[source] as usize..self.up_offset[source + 1] as usize], target)
+        } else {
+            (&self.down[self.down_offset[target] as usize..self.down_offset[target + 1] as usize], source)
+        };
+        let mut result: Option<ChEdge> = None;
+        for edge in edges.iter().filter(|edge| edge.neighbor as usize == neighbor) {
+            if result.is_none_or(|r| edge.weight < r.weight) {
+                result = Some(*edge);
+            }
+        }
+        return result;
+    }
+
+    /// replaces shortcuts by the nodes they skip, returns all nodes from source to target
+    pub fn unpack(&self, source: usize, target: usize, path: &mut Vec<usize>) {
+        match self.get_edge(source, target) {
+            Some(ChEdge { middle, .. }) if middle != NO_MIDDLE => {
+                self.unpack(source, middle as usize, path);
+                path.pop();
+                self.unpack(middle as usize, target, path);
+            }
+            _ => {
+                path.push(source);
+                path.push(target);
+            }
+        }
+    }
+}
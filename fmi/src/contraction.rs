@@ -0,0 +1,286 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use {ChEdge, ContractionHierarchy, get_gradient_percent, get_way_weight, is_way_allowed, MapData, NO_MIDDLE, Rider, TravelType};
+
+/// nodes a witness search may settle before the shortcut is added anyway
+const WITNESS_SEARCH_LIMIT: usize = 500;
+/// witnesses that are longer by rounding errors only make a shortcut unnecessary as well
+const WITNESS_TOLERANCE: f64 = 1.0 + 1e-9;
+/// smaller limit for estimating the amount of shortcuts of the nodes that are not contracted yet
+const ESTIMATE_SEARCH_LIMIT: usize = 50;
+
+#[derive(Copy, Clone)]
+struct Edge {
+    neighbor: usize,
+    middle: u32,
+    weight: f64,
+}
+
+struct Shortcut {
+    source: usize,
+    target: usize,
+    middle: usize,
+    weight: f64,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+struct State<K: PartialOrd> {
+    key: K,
+    node: usize,
+}
+
+impl<K: PartialOrd> Eq for State<K> {}
+
+// reversed, so the binary heap returns the smallest key first
+impl<K: PartialOrd> Ord for State<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        return other.key.partial_cmp(&self.key).unwrap_or(Ordering::Equal).then_with(|| other.node.cmp(&self.node));
+    }
+}
+
+impl<K: PartialOrd> PartialOrd for State<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// the graph that is left while nodes are contracted
+struct RemainingGraph {
+    outgoing: Vec<Vec<Edge>>,
+    incoming: Vec<Vec<Edge>>,
+}
+
+impl RemainingGraph {
+    /// adds an edge, parallel edges are merged into the cheapest one
+    fn add_edge(&mut self, source: usize, target: usize, middle: u32, weight: f64) {
+        match self.outgoing[source].iter_mut().find(|edge| edge.neighbor == target) {
+            Some(edge) if edge.weight <= weight => return,
+            Some(edge) => {
+                edge.weight = weight;
+                edge.middle = middle;
+            }
+            None => self.outgoing[source].push(Edge {
+                neighbor: target,
+                middle,
+                weight,
+            }),
+        }
+        match self.incoming[target].iter_mut().find(|edge| edge.neighbor == source) {
+            Some(edge) => {
+                edge.weight = weight;
+                edge.middle = middle;
+            }
+            None => self.incoming[target].push(Edge {
+                neighbor: source,
+                middle,
+                weight,
+            }),
+        }
+    }
+
+    fn remove_node(&mut self, node: usize) {
+        for edge in std::mem::take(&mut self.outgoing[node]) {
+            self.incoming[edge.neighbor].retain(|e| e.neighbor != node);
+        }
+        for edge in std::mem::take(&mut self.incoming[node]) {
+            self.outgoing[edge.neighbor].retain(|e| e.neighbor != node);
+        }
+    }
+}
+
+/// local Dijkstra searches that look for paths making a shortcut unnecessary
+struct WitnessSearch {
+    dist: Vec<f64>,
+    touched: Vec<usize>,
+    is_target: Vec<bool>,
+}
+
+impl WitnessSearch {
+    fn new(amount_nodes: usize) -> WitnessSearch {
+        return WitnessSearch {
+            dist: vec![f64::MAX; amount_nodes],
+            touched: Vec::new(),
+            is_target: vec![false; amount_nodes],
+        };
+    }
+
+    /// searches from source without using the skipped node until all targets are settled, max_weight or the settle limit is reached
+    fn run(&mut self, graph: &RemainingGraph, source: usize, skipped: usize, max_weight: f64, amount_targets: usize, limit: usize) {
+        for &node in self.touched.iter() {
+            self.dist[node] = f64::MAX;
+        }
+        self.touched.clear();
+        let mut heap = BinaryHeap::new();
+        self.dist[source] = 0.0;
+        self.touched.push(source);
+        heap.push(State { key: 0.0, node: source });
+        let mut settled = 0;
+        let mut settled_targets = 0;
+        while let Some(State { key, node }) = heap.pop() {
+            if key > self.dist[node] {
+                continue;
+            }
+            settled += 1;
+            if key > max_weight || settled > limit {
+                break;
+            }
+            if self.is_target[node] {
+                settled_targets += 1;
+                if settled_targets == amount_targets {
+                    break;
+                }
+            }
+            for edge in graph.outgoing[node].iter() {
+                if edge.neighbor == skipped {
+                    continue;
+                }
+                let next = key + edge.weight;
+                if next < self.dist[edge.neighbor] {
+                    if self.dist[edge.neighbor] == f64::MAX {
+                        self.touched.push(edge.neighbor);
+                    }
+                    self.dist[edge.neighbor] = next;
+                    heap.push(State { key: next, node: edge.neighbor });
+                }
+            }
+        }
+    }
+
+    /// returns the shortcuts needed to keep all distances when the node is removed
+    fn find_shortcuts(&mut self, graph: &RemainingGraph, node: usize, limit: usize) -> Vec<Shortcut> {
+        let mut shortcuts = Vec::new();
+        let max_outgoing = graph.outgoing[node].iter().map(|edge| edge.weight).fold(0.0, f64::max);
+        for outgoing in graph.outgoing[node].iter() {
+            self.is_target[outgoing.neighbor] = true;
+        }
+        for incoming in graph.incoming[node].iter() {
+            if graph.outgoing[node].iter().all(|outgoing| outgoing.neighbor == incoming.neighbor) {
+                continue;
+            }
+            // the source itself is settled first and counts as a target if it is one
+            let amount_targets = graph.outgoing[node].len();
+            self.run(graph, incoming.neighbor, node, incoming.weight + max_outgoing, amount_targets, limit);
+            for outgoing in graph.outgoing[node].iter() {
+                let weight = incoming.weight + outgoing.weight;
+                if outgoing.neighbor != incoming.neighbor && self.dist[outgoing.neighbor] > weight * WITNESS_TOLERANCE {
+                    shortcuts.push(Shortcut {
+                        source: incoming.neighbor,
+                        target: outgoing.neighbor,
+                        middle: node,
+                        weight,
+                    });
+                }
+            }
+        }
+        for outgoing in graph.outgoing[node].iter() {
+            self.is_target[outgoing.neighbor] = false;
+        }
+        return shortcuts;
+    }
+}
+
+/// edge difference plus the amount of contracted neighbors, so the contraction spreads evenly over the graph
+fn get_priority(graph: &RemainingGraph, node: usize, amount_shortcuts: usize, contracted_neighbors: &[i64]) -> i64 {
+    let amount_edges = graph.outgoing[node].len() + graph.incoming[node].len();
+    return amount_shortcuts as i64 - amount_edges as i64 + contracted_neighbors[node];
+}
+
+/// orders the nodes by importance and adds shortcuts for every node that is removed from the graph
+pub fn contract(map_data: &MapData, travel_type: TravelType, by_distance: bool) -> ContractionHierarchy {
+    let amount_nodes = map_data.nodes.len();
+    let mut graph = RemainingGraph {
+        outgoing: vec![Vec::new(); amount_nodes],
+        incoming: vec![Vec::new(); amount_nodes],
+    };
+    for way in map_data.ways.iter() {
+        if way.source != way.target && is_way_allowed(way, travel_type) {
+            let weight = get_way_weight(way, travel_type, by_distance, get_gradient_percent(way, &map_data.nodes), &Rider::DEFAULT);
+            graph.add_edge(way.source as usize, way.target as usize, NO_MIDDLE, weight);
+        }
+    }
+
+    let mut witness_search = WitnessSearch::new(amount_nodes);
+    let mut contracted_neighbors = vec![0i64; amount_nodes];
+    let mut priorities = vec![0i64; amount_nodes];
+    let mut queue = BinaryHeap::with_capacity(amount_nodes);
+    for (node, priority) in priorities.iter_mut().enumerate() {
+        let amount_shortcuts = witness_search.find_shortcuts(&graph, node, ESTIMATE_SEARCH_LIMIT).len();
+        *priority = get_priority(&graph, node, amount_shortcuts, &contracted_neighbors);
+        queue.push(State { key: *priority, node });
+    }
+
+    let mut is_contracted = vec![false; amount_nodes];
+    let mut ranks = vec![0u32; amount_nodes];
+    let mut up = vec![Vec::<ChEdge>::new(); amount_nodes];
+    let mut down = vec![Vec::<ChEdge>::new(); amount_nodes];
+    let mut rank = 0;
+    while let Some(State { key, node }) = queue.pop() {
+        // outdated entry, the node was put into the queue again with its new priority
+        if is_contracted[node] || key != priorities[node] {
+            continue;
+        }
+        let shortcuts = witness_search.find_shortcuts(&graph, node, WITNESS_SEARCH_LIMIT);
+        is_contracted[node] = true;
+        ranks[node] = rank;
+        rank += 1;
+        let mut neighbors = Vec::new();
+        for edge in graph.outgoing[node].iter() {
+            up[node].push(ChEdge {
+                neighbor: edge.neighbor as u32,
+                middle: edge.middle,
+                weight: edge.weight,
+            });
+            neighbors.push(edge.neighbor);
+        }
+        for edge in graph.incoming[node].iter() {
+            down[node].push(ChEdge {
+                neighbor: edge.neighbor as u32,
+                middle: edge.middle,
+                weight: edge.weight,
+            });
+            neighbors.push(edge.neighbor);
+        }
+        graph.remove_node(node);
+        for shortcut in shortcuts {
+            graph.add_edge(shortcut.source, shortcut.target, shortcut.middle as u32, shortcut.weight);
+        }
+
+        // the priorities of the neighbors changed with the removed edges and new shortcuts
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        for neighbor in neighbors {
+            contracted_neighbors[neighbor] += 1;
+            let amount_shortcuts = witness_search.find_shortcuts(&graph, neighbor, ESTIMATE_SEARCH_LIMIT).len();
+            let priority = get_priority(&graph, neighbor, amount_shortcuts, &contracted_neighbors);
+            if priority != priorities[neighbor] {
+                priorities[neighbor] = priority;
+                queue.push(State { key: priority, node: neighbor });
+            }
+        }
+    }
+
+    let (up, up_offset) = flatten(up);
+    let (down, down_offset) = flatten(down);
+    return ContractionHierarchy {
+        travel_type: travel_type as u32,
+        by_distance,
+        ranks: ranks.into(),
+        up: up.into(),
+        up_offset: up_offset.into(),
+        down: down.into(),
+        down_offset: down_offset.into(),
+    };
+}
+
+/// turns edge lists into an adjacency array
+fn flatten(edge_lists: Vec<Vec<ChEdge>>) -> (Vec<ChEdge>, Vec<u32>) {
+    let mut offset = Vec::with_capacity(edge_lists.len() + 1);
+    let mut edges = Vec::new();
+    offset.push(0);
+    for list in edge_lists {
+        edges.extend(list);
+        offset.push(edges.len() as u32);
+    }
+    return (edges, offset);
+}
@@ -0,0 +1,329 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use memmap::Mmap;
+
+use {ContractionHierarchy, Grid, Header, MapData, NO_MIDDLE, Record, Storage, TravelType};
+
+/// sections start at multiples of this, so memory mapped records can be accessed in place
+const ALIGNMENT: usize = 8;
+/// nodes, ways, offset, reverse way ids, reverse offset, grid cells, grid node ids
+const SECTION_COUNT: usize = 7;
+/// travel type and by_distance flag of a contraction hierarchy, followed by the lengths of its sections
+const CH_FIELD_COUNT: usize = 2;
+/// ranks, up edges, up offset, down edges, down offset
+const CH_SECTION_COUNT: usize = 5;
+
+fn get_padding(position: usize) -> usize {
+    return (ALIGNMENT - position % ALIGNMENT) % ALIGNMENT;
+}
+
+struct CountingWriter<W: Write> {
+    inner: W,
+    position: usize,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.position += written;
+        return Ok(written);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.inner.flush();
+    }
+}
+
+impl<W: Write> CountingWriter<W> {
+    fn write_padding(&mut self) -> io::Result<()> {
+        let padding = get_padding(self.position);
+        return self.write_all(&[0u8; ALIGNMENT][..padding]);
+    }
+}
+
+struct CountingReader<R: Read> {
+    inner: R,
+    position: usize,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position += read;
+        return Ok(read);
+    }
+}
+
+impl<R: Read> CountingReader<R> {
+    fn skip_padding(&mut self) -> io::Result<()> {
+        let padding = get_padding(self.position);
+        return self.read_exact(&mut [0u8; ALIGNMENT][..padding]);
+    }
+}
+
+/// writes magic number, format version, header and graph
+pub fn write_fmi<W: Write>(writer: &mut W, header: &Header, map_data: &MapData) -> Result<(), String> {
+    let mut writer = CountingWriter {
+        inner: writer,
+        position: 0,
+    };
+    header.write_to(&mut writer).map_err(|e| format!("could not write header: {}", e))?;
+    return write_sections(&mut writer, map_data).map_err(|e| format!("could not write graph: {}", e));
+}
+
+fn write_sections<W: Write>(writer: &mut CountingWriter<W>, map_data: &MapData) -> io::Result<()> {
+    writer.write_padding()?;
+    let lengths = [
+        map_data.nodes.len(),
+        map_data.ways.len(),
+        map_data.offset.len(),
+        map_data.reverse_way_ids.len(),
+        map_data.reverse_offset.len(),
+        map_data.grid.cells.len(),
+        map_data.grid.node_ids.len(),
+    ];
+    write_u64s(writer, &lengths)?;
+    write_section(writer, &map_data.nodes)?;
+    write_section(writer, &map_data.ways)?;
+    write_section(writer, &map_data.offset)?;
+    write_section(writer, &map_data.reverse_way_ids)?;
+    write_section(writer, &map_data.reverse_offset)?;
+    write_section(writer, &map_data.grid.cells)?;
+    write_section(writer, &map_data.grid.node_ids)?;
+    write_u64s(writer, &[map_data.hierarchies.len()])?;
+    for ch in map_data.hierarchies.iter() {
+        write_u64s(writer, &[
+            ch.travel_type as usize,
+            ch.by_distance as usize,
+            ch.ranks.len(),
+            ch.up.len(),
+            ch.up_offset.len(),
+            ch.down.len(),
+            ch.down_offset.len(),
+        ])?;
+        write_section(writer, &ch.ranks)?;
+        write_section(writer, &ch.up)?;
+        write_section(writer, &ch.up_offset)?;
+        write_section(writer, &ch.down)?;
+        write_section(writer, &ch.down_offset)?;
+    }
+    return writer.flush();
+}
+
+fn write_u64s<W: Write>(writer: &mut CountingWriter<W>, values: &[usize]) -> io::Result<()> {
+    for value in values {
+        writer.write_all(&(*value as u64).to_le_bytes())?;
+    }
+    return Ok(());
+}
+
+fn write_section<T: Record, W: Write>(writer: &mut CountingWriter<W>, records: &[T]) -> io::Result<()> {
+    let mut buf = Vec::<u8>::with_capacity(1 << 16);
+    for record in records {
+        record.write_le(&mut buf);
+        if buf.len() >= 1 << 16 {
+            writer.write_all(&buf)?;
+            buf.clear();
+        }
+    }
+    writer.write_all(&buf)?;
+    return writer.write_padding();
+}
+
+/// reads an fmi file into memory, magic number and format version are checked before the graph is read
+pub fn read_fmi<R: Read>(reader: &mut R) -> Result<(Header, MapData), String> {
+    let mut reader = CountingReader {
+        inner: reader,
+        position: 0,
+    };
+    let header = Header::read_from(&mut reader)?;
+    let map_data = read_sections(&mut reader).map_err(|e| format!("graph is corrupt: {}", e))?;
+    validate(&map_data)?;
+    return Ok((header, map_data));
+}
+
+fn read_sections<R: Read>(reader: &mut CountingReader<R>) -> io::Result<MapData> {
+    reader.skip_padding()?;
+    let lengths = read_u64s(reader, SECTION_COUNT)?;
+    let mut map_data = MapData {
+        nodes: read_section(reader, lengths[0])?,
+        ways: read_section(reader, lengths[1])?,
+        offset: read_section(reader, lengths[2])?,
+        reverse_way_ids: read_section(reader, lengths[3])?,
+        reverse_offset: read_section(reader, lengths[4])?,
+        grid: Grid {
+            cells: read_section(reader, lengths[5])?,
+            node_ids: read_section(reader, lengths[6])?,
+        },
+        hierarchies: Vec::new(),
+    };
+    let amount_hierarchies = read_u64s(reader, 1)?[0];
+    for _ in 0..amount_hierarchies {
+        let fields = read_u64s(reader, CH_FIELD_COUNT + CH_SECTION_COUNT)?;
+        map_data.hierarchies.push(ContractionHierarchy {
+            travel_type: fields[0] as u32,
+            by_distance: fields[1] != 0,
+            ranks: read_section(reader, fields[2])?,
+            up: read_section(reader, fields[3])?,
+            up_offset: read_section(reader, fields[4])?,
+            down: read_section(reader, fields[5])?,
+            down_offset: read_section(reader, fields[6])?,
+        });
+    }
+    return Ok(map_data);
+}
+
+fn read_u64s<R: Read>(reader: &mut CountingReader<R>, amount: usize) -> io::Result<Vec<usize>> {
+    let mut values = Vec::with_capacity(amount);
+    for _ in 0..amount {
+        let mut bytes = [0u8; 8];
+        reader.read_exact(&mut bytes)?;
+        values.push(u64::from_le_bytes(bytes) as usize);
+    }
+    return Ok(values);
+}
+
+fn read_section<T: Record, R: Read>(reader: &mut CountingReader<R>, len: usize) -> io::Result<Storage<T>> {
+    const CHUNK: usize = 4096;
+    // the length is not trusted for the allocation, a corrupt file fails at read_exact instead
+    let mut records = Vec::<T>::with_capacity(len.min(1 << 20));
+    let mut buf = vec![0u8; CHUNK * T::SIZE];
+    let mut remaining = len;
+    while remaining > 0 {
+        let amount = remaining.min(CHUNK);
+        reader.read_exact(&mut buf[..amount * T::SIZE])?;
+        for i in 0..amount {
+            records.push(T::read_le(&buf[i * T::SIZE..]));
+        }
+        remaining -= amount;
+    }
+    reader.skip_padding()?;
+    return Ok(Storage::Owned(records));
+}
+
+/// memory maps an fmi file, the graph is used in place instead of being copied into memory
+///
+/// The file must not be modified while it is mapped.
+pub fn map_fmi(path: &Path) -> Result<(Header, MapData), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    if cfg!(target_endian = "big") {
+        // records are stored little endian and can only be used in place on little endian machines
+        return read_fmi(&mut BufReader::new(file));
+    }
+    let mmap = Arc::new(unsafe { Mmap::map(&file) }.map_err(|e| format!("could not map file: {}", e))?);
+    let mut reader: &[u8] = &mmap;
+    let header = Header::read_from(&mut reader)?;
+    let mut position = mmap.len() - reader.len();
+    position += get_padding(position);
+    let lengths = map_u64s(&mmap, &mut position, SECTION_COUNT)?;
+    let mut map_data = MapData {
+        nodes: map_section(&mmap, &mut position, lengths[0])?,
+        ways: map_section(&mmap, &mut position, lengths[1])?,
+        offset: map_section(&mmap, &mut position, lengths[2])?,
+        reverse_way_ids: map_section(&mmap, &mut position, lengths[3])?,
+        reverse_offset: map_section(&mmap, &mut position, lengths[4])?,
+        grid: Grid {
+            cells: map_section(&mmap, &mut position, lengths[5])?,
+            node_ids: map_section(&mmap, &mut position, lengths[6])?,
+        },
+        hierarchies: Vec::new(),
+    };
+    let amount_hierarchies = map_u64s(&mmap, &mut position, 1)?[0];
+    for _ in 0..amount_hierarchies {
+        let fields = map_u64s(&mmap, &mut position, CH_FIELD_COUNT + CH_SECTION_COUNT)?;
+        map_data.hierarchies.push(ContractionHierarchy {
+            travel_type: fields[0] as u32,
+            by_distance: fields[1] != 0,
+            ranks: map_section(&mmap, &mut position, fields[2])?,
+            up: map_section(&mmap, &mut position, fields[3])?,
+            up_offset: map_section(&mmap, &mut position, fields[4])?,
+            down: map_section(&mmap, &mut position, fields[5])?,
+            down_offset: map_section(&mmap, &mut position, fields[6])?,
+        });
+    }
+    validate(&map_data)?;
+    return Ok((header, map_data));
+}
+
+fn map_u64s(mmap: &Mmap, position: &mut usize, amount: usize) -> Result<Vec<usize>, String> {
+    if mmap.len() < *position + 8 * amount {
+        return Err("graph is corrupt: section lengths are missing".to_string());
+    }
+    let mut values = Vec::with_capacity(amount);
+    for _ in 0..amount {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&mmap[*position..*position + 8]);
+        values.push(u64::from_le_bytes(bytes) as usize);
+        *position += 8;
+    }
+    return Ok(values);
+}
+
+fn map_section<T: Record>(mmap: &Arc<Mmap>, position: &mut usize, len: usize) -> Result<Storage<T>, String> {
+    let storage = Storage::mapped(mmap, *position, len).map_err(|e| format!("graph is corrupt: {}", e))?;
+    *position += storage.byte_size();
+    *position += get_padding(*position);
+    return Ok(storage);
+}
+
+/// checks lengths and indices of all sections, so a corrupt file fails to load instead of panicking in a search
+///
+/// Every record is read once, which touches all pages of a mapped file.
+fn validate(map_data: &MapData) -> Result<(), String> {
+    let amount_nodes = map_data.nodes.len();
+    if map_data.offset.len() != amount_nodes + 1 {
+        return Err("graph is corrupt: offset array does not match the amount of nodes".to_string());
+    }
+    if !is_valid_offset(&map_data.offset, map_data.ways.len()) {
+        return Err("graph is corrupt: offset array does not match the amount of ways".to_string());
+    }
+    for node in 0..amount_nodes {
+        let ways = &map_data.ways[map_data.offset[node] as usize..map_data.offset[node + 1] as usize];
+        if ways.iter().any(|way| way.source as usize != node || way.target as usize >= amount_nodes) {
+            return Err("graph is corrupt: ways do not match their nodes".to_string());
+        }
+    }
+    if map_data.reverse_offset.len() != amount_nodes + 1
+        || map_data.reverse_way_ids.len() != map_data.ways.len()
+        || !is_valid_offset(&map_data.reverse_offset, map_data.ways.len()) {
+        return Err("graph is corrupt: reverse adjacency does not match the amount of nodes and ways".to_string());
+    }
+    for node in 0..amount_nodes {
+        let way_ids = &map_data.reverse_way_ids[map_data.reverse_offset[node] as usize..map_data.reverse_offset[node + 1] as usize];
+        if way_ids.iter().any(|&id| id as usize >= map_data.ways.len() || map_data.ways[id as usize].target as usize != node) {
+            return Err("graph is corrupt: reverse adjacency does not match the ways".to_string());
+        }
+    }
+    if map_data.grid.node_ids.len() != amount_nodes {
+        return Err("graph is corrupt: grid does not contain every node".to_string());
+    }
+    let grid = &map_data.grid;
+    if grid.cells.iter().any(|cell| cell.start > cell.end || cell.end as usize > grid.node_ids.len())
+        || grid.cells.windows(2).any(|cells| (cells[0].latitude, cells[0].longitude) >= (cells[1].latitude, cells[1].longitude))
+        || grid.node_ids.iter().any(|&id| id as usize >= amount_nodes) {
+        return Err("graph is corrupt: grid does not match the nodes".to_string());
+    }
+    for ch in map_data.hierarchies.iter() {
+        if TravelType::from_u32(ch.travel_type).is_none()
+            || ch.ranks.len() != amount_nodes
+            || ch.up_offset.len() != amount_nodes + 1
+            || ch.down_offset.len() != amount_nodes + 1
+            || !is_valid_offset(&ch.up_offset, ch.up.len())
+            || !is_valid_offset(&ch.down_offset, ch.down.len())
+            || ch.ranks.iter().any(|&rank| rank as usize >= amount_nodes)
+            || ch.up.iter().chain(ch.down.iter()).any(|edge| {
+                edge.neighbor as usize >= amount_nodes || (edge.middle != NO_MIDDLE && edge.middle as usize >= amount_nodes)
+            }) {
+            return Err("graph is corrupt: contraction hierarchy does not match the graph".to_string());
+        }
+    }
+    return Ok(());
+}
+
+/// offsets start at 0, never decrease and end at the amount of records they point into
+fn is_valid_offset(offset: &[u32], len: usize) -> bool {
+    return offset.first() == Some(&0) && offset.windows(2).all(|pair| pair[0] <= pair[1]) && offset.last() == Some(&(len as u32));
+}
@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bincode::{deserialize_from, serialize_into};
+use crc32fast::Hasher;
+use serde::{Deserialize, Serialize};
+
+use {BoundingBox, Node};
+
+/// first bytes of every fmi file, the line break detects files mangled by text mode transfers
+pub const MAGIC_NUMBER: [u8; 8] = *b"OSMFMI\r\n";
+/// has to be increased whenever the serialized layout of `MapData` or `Header` or the weights of the contraction hierarchies change
+pub const FORMAT_VERSION: u32 = 7;
+
+/// describes the graph of an fmi file, written between the version and the graph itself
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Header {
+    /// seconds since the unix epoch, taken from SOURCE_DATE_EPOCH if set to get reproducible files
+    pub created_at: u64,
+    pub source_name: String,
+    pub bounding_box: BoundingBox,
+    /// crc32 of the osm pbf file the graph was created from
+    pub source_checksum: u32,
+}
+
+impl Header {
+    pub fn new(source: &Path, nodes: &[Node]) -> io::Result<Header> {
+        let created_at = match std::env::var("SOURCE_DATE_EPOCH").ok().and_then(|epoch| epoch.parse::<u64>().ok()) {
+            Some(epoch) => epoch,
+            None => SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        };
+        return Ok(Header {
+            created_at,
+            source_name: source.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+            bounding_box: BoundingBox::from_nodes(nodes),
+            source_checksum: get_checksum(source)?,
+        });
+    }
+
+    /// writes magic number, format version and header
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> bincode::Result<()> {
+        writer.write_all(&MAGIC_NUMBER)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        return serialize_into(writer, self);
+    }
+
+    /// reads and checks magic number and format version, then returns the header
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Header, String> {
+        let mut magic_number = [0u8; 8];
+        reader.read_exact(&mut magic_number).map_err(|e| format!("could not read magic number: {}", e))?;
+        if magic_number != MAGIC_NUMBER {
+            return Err("not an fmi file (wrong magic number), it may have been created by an outdated version of pre".to_string());
+        }
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version).map_err(|e| format!("could not read format version: {}", e))?;
+        let version = u32::from_le_bytes(version);
+        if version != FORMAT_VERSION {
+            return Err(format!("fmi format version {} is not supported, expected version {}. Re-create the file with a matching version of pre", version, FORMAT_VERSION));
+        }
+        return deserialize_from(reader).map_err(|e| format!("header is corrupt: {}", e));
+    }
+}
+
+/// crc32 of the whole file
+fn get_checksum(path: &Path) -> io::Result<u32> {
+    let mut file = File::open(path)?;
+    let mut hasher = Hasher::new();
+    let mut buf = vec![0u8; 1 << 16];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    return Ok(hasher.finalize());
+}
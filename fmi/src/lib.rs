@@ -0,0 +1,496 @@
+//! Graph data model shared by `pre` and `web` and the `.fmi` file format they exchange.
+//!
+//! An `.fmi` file consists of the magic number, the format version, a [`Header`] and the [`MapData`] as flat
+//! arrays of fixed size records. Use [`write_fmi`], [`read_fmi`] and [`map_fmi`] instead of (de)serializing the
+//! parts on your own. [`map_fmi`] memory maps the file, so loading is independent of the graph size and
+//! several processes serving the same file share its pages.
+
+// explicit returns are the code style of this project
+#![allow(clippy::needless_return)]
+
+extern crate bincode;
+extern crate crc32fast;
+extern crate memmap;
+extern crate serde;
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+pub use ch::{ChEdge, ContractionHierarchy, NO_MIDDLE};
+pub use format::{map_fmi, read_fmi, write_fmi};
+pub use header::{FORMAT_VERSION, Header, MAGIC_NUMBER};
+pub use storage::{Record, Storage};
+
+mod ch;
+mod contraction;
+mod format;
+mod header;
+mod storage;
+#[cfg(test)]
+mod tests;
+
+// First three digits of coordinates are used for the grid hashing
+pub const GRID_MULTIPLICATOR: usize = 100;
+
+#[repr(C)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub struct Way {
+    pub source: u32,
+    pub target: u32,
+    /// in km/h
+    pub speed: u32,
+    /// in km
+    pub distance: f32,
+    /// road class of the way, see [`RoadClass`]
+    pub travel_type: u32,
+    /// see [`Surface`]
+    pub surface: u32,
+}
+
+unsafe impl Record for Way {
+    const SIZE: usize = 24;
+
+    fn write_le(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.source.to_le_bytes());
+        buf.extend_from_slice(&self.target.to_le_bytes());
+        buf.extend_from_slice(&self.speed.to_le_bytes());
+        buf.extend_from_slice(&self.distance.to_bits().to_le_bytes());
+        buf.extend_from_slice(&self.travel_type.to_le_bytes());
+        buf.extend_from_slice(&self.surface.to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        return Way {
+            source: u32::read_le(&bytes[0..]),
+            target: u32::read_le(&bytes[4..]),
+            speed: u32::read_le(&bytes[8..]),
+            distance: f32::from_bits(u32::read_le(&bytes[12..])),
+            travel_type: u32::read_le(&bytes[16..]),
+            surface: u32::read_le(&bytes[20..]),
+        };
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub struct Node {
+    pub latitude: f32,
+    pub longitude: f32,
+    /// in m
+    pub elevation: f32,
+}
+
+unsafe impl Record for Node {
+    const SIZE: usize = 12;
+
+    fn write_le(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.latitude.to_bits().to_le_bytes());
+        buf.extend_from_slice(&self.longitude.to_bits().to_le_bytes());
+        buf.extend_from_slice(&self.elevation.to_bits().to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        return Node {
+            latitude: f32::from_bits(u32::read_le(&bytes[0..])),
+            longitude: f32::from_bits(u32::read_le(&bytes[4..])),
+            elevation: f32::from_bits(u32::read_le(&bytes[8..])),
+        };
+    }
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min_latitude: f32,
+    pub min_longitude: f32,
+    pub max_latitude: f32,
+    pub max_longitude: f32,
+}
+
+impl BoundingBox {
+    /// returns the smallest bounding box containing all nodes
+    pub fn from_nodes(nodes: &[Node]) -> BoundingBox {
+        if nodes.is_empty() {
+            return BoundingBox {
+                min_latitude: 0.0,
+                min_longitude: 0.0,
+                max_latitude: 0.0,
+                max_longitude: 0.0,
+            };
+        }
+        let mut bounding_box = BoundingBox {
+            min_latitude: f32::MAX,
+            min_longitude: f32::MAX,
+            max_latitude: f32::MIN,
+            max_longitude: f32::MIN,
+        };
+        for node in nodes {
+            bounding_box.min_latitude = bounding_box.min_latitude.min(node.latitude);
+            bounding_box.min_longitude = bounding_box.min_longitude.min(node.longitude);
+            bounding_box.max_latitude = bounding_box.max_latitude.max(node.latitude);
+            bounding_box.max_longitude = bounding_box.max_longitude.max(node.longitude);
+        }
+        return bounding_box;
+    }
+
+    pub fn contains(&self, latitude: f32, longitude: f32) -> bool {
+        return latitude >= self.min_latitude
+            && latitude <= self.max_latitude
+            && longitude >= self.min_longitude
+            && longitude <= self.max_longitude;
+    }
+}
+
+/// non-empty cell of the grid, its nodes are node_ids[start..end]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GridCell {
+    pub latitude: i32,
+    pub longitude: i32,
+    pub start: u32,
+    pub end: u32,
+}
+
+unsafe impl Record for GridCell {
+    const SIZE: usize = 16;
+
+    fn write_le(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.latitude.to_le_bytes());
+        buf.extend_from_slice(&self.longitude.to_le_bytes());
+        buf.extend_from_slice(&self.start.to_le_bytes());
+        buf.extend_from_slice(&self.end.to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        return GridCell {
+            latitude: u32::read_le(&bytes[0..]) as i32,
+            longitude: u32::read_le(&bytes[4..]) as i32,
+            start: u32::read_le(&bytes[8..]),
+            end: u32::read_le(&bytes[12..]),
+        };
+    }
+}
+
+/// flattened spatial index, cells are sorted by (latitude, longitude)
+#[derive(Clone, Debug)]
+pub struct Grid {
+    pub cells: Storage<GridCell>,
+    pub node_ids: Storage<u32>,
+}
+
+impl Grid {
+    pub fn new(nodes: &[Node]) -> Grid {
+        let mut grid = BTreeMap::<(i32, i32), Vec<u32>>::new();
+        for (id, node) in nodes.iter().enumerate() {
+            grid.entry(get_grid_cell(node.latitude, node.longitude)).or_default().push(id as u32);
+        }
+        let mut cells = Vec::<GridCell>::with_capacity(grid.len());
+        let mut node_ids = Vec::<u32>::with_capacity(nodes.len());
+        for ((latitude, longitude), ids) in grid {
+            cells.push(GridCell {
+                latitude,
+                longitude,
+                start: node_ids.len() as u32,
+                end: (node_ids.len() + ids.len()) as u32,
+            });
+            node_ids.extend(ids);
+        }
+        return Grid {
+            cells: cells.into(),
+            node_ids: node_ids.into(),
+        };
+    }
+
+    /// returns the node ids in the given cell
+    pub fn get(&self, cell: &(i32, i32)) -> Option<&[u32]> {
+        return match self.cells.binary_search_by_key(cell, |c| (c.latitude, c.longitude)) {
+            Ok(index) => {
+                let cell = self.cells[index];
+                Some(&self.node_ids[cell.start as usize..cell.end as usize])
+            }
+            Err(_) => None,
+        };
+    }
+}
+
+/// the routing graph as adjacency array
+#[derive(Clone, Debug)]
+pub struct MapData {
+    pub nodes: Storage<Node>,
+    /// sorted by source
+    pub ways: Storage<Way>,
+    /// outgoing ways of node i are ways[offset[i]..offset[i + 1]]
+    pub offset: Storage<u32>,
+    /// indices into ways sorted by target
+    pub reverse_way_ids: Storage<u32>,
+    /// incoming ways of node i are ways[reverse_way_ids[reverse_offset[i]..reverse_offset[i + 1]]]
+    pub reverse_offset: Storage<u32>,
+    pub grid: Grid,
+    /// optional, created by `pre --ch` for plain shortest path queries
+    pub hierarchies: Vec<ContractionHierarchy>,
+}
+
+impl MapData {
+    /// sorts the ways by source and creates offset arrays and grid
+    pub fn new(nodes: Vec<Node>, mut ways: Vec<Way>) -> MapData {
+        ways.sort_by_key(|way| way.source);
+        let mut offset = vec![0; nodes.len() + 1];
+        fill_offset(&ways, &mut offset);
+        let mut reverse_way_ids: Vec<u32> = (0..ways.len() as u32).collect();
+        reverse_way_ids.sort_by_key(|&id| ways[id as usize].target);
+        let mut reverse_offset = vec![0; nodes.len() + 1];
+        fill_reverse_offset(&ways, &mut reverse_offset);
+        let grid = Grid::new(&nodes);
+        return MapData {
+            nodes: nodes.into(),
+            ways: ways.into(),
+            offset: offset.into(),
+            reverse_way_ids: reverse_way_ids.into(),
+            reverse_offset: reverse_offset.into(),
+            grid,
+            hierarchies: Vec::new(),
+        };
+    }
+
+    /// size of all arrays in bytes
+    pub fn byte_size(&self) -> usize {
+        return self.nodes.byte_size()
+            + self.ways.byte_size()
+            + self.offset.byte_size()
+            + self.reverse_way_ids.byte_size()
+            + self.reverse_offset.byte_size()
+            + self.grid.cells.byte_size()
+            + self.grid.node_ids.byte_size()
+            + self.hierarchies.iter().map(|ch| ch.byte_size()).sum::<usize>();
+    }
+}
+
+/// which travel types may use a way, stored as `Way::travel_type`
+/// infos from https://wiki.openstreetmap.org/wiki/Key:highway
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RoadClass {
+    CarOnly = 0,
+    CarAndBicycle = 1,
+    Bicycle = 2,
+    BicycleAndFoot = 3,
+    Foot = 4,
+    All = 5,
+}
+
+impl RoadClass {
+    /// None for values that are no road class
+    pub fn from_u32(value: u32) -> Option<RoadClass> {
+        return match value {
+            0 => Some(RoadClass::CarOnly),
+            1 => Some(RoadClass::CarAndBicycle),
+            2 => Some(RoadClass::Bicycle),
+            3 => Some(RoadClass::BicycleAndFoot),
+            4 => Some(RoadClass::Foot),
+            5 => Some(RoadClass::All),
+            _ => None,
+        };
+    }
+}
+
+/// surface of a way from its surface tag, stored as `Way::surface`
+/// infos from https://wiki.openstreetmap.org/wiki/Key:surface
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Surface {
+    /// no or an unknown surface tag
+    Unknown = 0,
+    /// asphalt, concrete or paving stones
+    Paved = 1,
+    /// sett and cobblestones
+    Cobblestone = 2,
+    /// compacted and fine gravel
+    Compacted = 3,
+    /// gravel, dirt, grass, sand and everything else without a solid top
+    Unpaved = 4,
+}
+
+impl Surface {
+    /// unknown values are taken as unknown surface
+    pub fn from_u32(value: u32) -> Surface {
+        return match value {
+            1 => Surface::Paved,
+            2 => Surface::Cobblestone,
+            3 => Surface::Compacted,
+            4 => Surface::Unpaved,
+            _ => Surface::Unknown,
+        };
+    }
+}
+
+/// how a route is travelled, every travel type has its own contraction hierarchies
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TravelType {
+    Car = 0,
+    Bicycle = 1,
+    Foot = 2,
+}
+
+impl TravelType {
+    pub const ALL: [TravelType; 3] = [TravelType::Car, TravelType::Bicycle, TravelType::Foot];
+
+    /// None for values that are no travel type
+    pub fn from_u32(value: u32) -> Option<TravelType> {
+        return TravelType::ALL.iter().cloned().find(|&travel_type| travel_type as u32 == value);
+    }
+
+    /// parses the names used by the web api, None if the name is unknown
+    pub fn from_name(name: &str) -> Option<TravelType> {
+        return TravelType::ALL.iter().cloned().find(|travel_type| travel_type.name() == name);
+    }
+
+    pub fn name(self) -> &'static str {
+        return match self {
+            TravelType::Car => "car",
+            TravelType::Bicycle => "bicycle",
+            TravelType::Foot => "foot",
+        };
+    }
+
+    /// the road classes the travel type may use, snapping and all searches go by this
+    pub fn may_use(self, road_class: RoadClass) -> bool {
+        return match self {
+            TravelType::Car => matches!(road_class, RoadClass::CarOnly | RoadClass::CarAndBicycle | RoadClass::All),
+            TravelType::Bicycle => matches!(road_class, RoadClass::CarAndBicycle | RoadClass::Bicycle | RoadClass::BicycleAndFoot | RoadClass::All),
+            TravelType::Foot => matches!(road_class, RoadClass::BicycleAndFoot | RoadClass::Foot | RoadClass::All),
+        };
+    }
+}
+
+/// checks if a way can be used with the travel type, ways with an unknown road class can not be used at all
+pub fn is_way_allowed(way: &Way, travel_type: TravelType) -> bool {
+    return match RoadClass::from_u32(way.travel_type) {
+        Some(road_class) => travel_type.may_use(road_class),
+        None => false,
+    };
+}
+
+/// bicycles brake to at most this speed in km/h downhill
+pub const MAX_BICYCLE_SPEED: f64 = 40.0;
+/// walking speed in km/h on flat ground
+pub const WALKING_SPEED: f64 = 7.0;
+/// gravitational acceleration in m/s^2
+const GRAVITY: f64 = 9.81;
+/// rolling resistance coefficient of touring tyres on asphalt
+const ROLLING_RESISTANCE: f64 = 0.007;
+/// air density in kg/m^3 times drag coefficient and frontal area in m^2 of an upright rider, halved
+const AIR_DRAG: f64 = 0.5 * 1.2 * 0.45;
+
+/// rider of the power based speed model of bicycles
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rider {
+    /// sustained power in W
+    pub power: f64,
+    /// rider and bicycle in kg
+    pub mass: f64,
+}
+
+impl Rider {
+    /// leisure rider with about 20 km/h on flat ground, the contraction hierarchies of pre are weighted for this rider
+    pub const DEFAULT: Rider = Rider {
+        power: 80.0,
+        mass: 85.0,
+    };
+
+    /// speed in km/h at which the power equals the losses to gravity, rolling resistance and air drag on the gradient
+    /// in percent, at most MAX_BICYCLE_SPEED
+    pub fn get_speed(&self, gradient_percent: f64) -> f64 {
+        let slope = gradient_percent / 100.0;
+        let hypotenuse = (1.0 + slope * slope).sqrt();
+        // force in N that does not depend on the speed
+        let force = self.mass * GRAVITY * (slope + ROLLING_RESISTANCE) / hypotenuse;
+        // power = force * v + AIR_DRAG * v^3, so v is the positive root of v^3 + p * v + q
+        let p = force / AIR_DRAG;
+        let q = -self.power / AIR_DRAG;
+        let discriminant = (q / 2.0).powi(2) + (p / 3.0).powi(3);
+        let speed = if discriminant >= 0.0 {
+            (-q / 2.0 + discriminant.sqrt()).cbrt() + (-q / 2.0 - discriminant.sqrt()).cbrt()
+        } else {
+            // steep descents have three real roots, the largest one is the positive one
+            2.0 * (-p / 3.0).sqrt() * ((3.0 * q / (2.0 * p) * (-3.0 / p).sqrt()).acos() / 3.0).cos()
+        };
+        return (speed * 3.6).min(MAX_BICYCLE_SPEED);
+    }
+}
+
+/// walking speed in km/h on the gradient in percent by Tobler's hiking function, scaled to WALKING_SPEED on flat
+/// ground. Walkers are fastest on a descent of 5 %
+pub fn get_walking_speed(gradient_percent: f64) -> f64 {
+    let slope = gradient_percent / 100.0;
+    return WALKING_SPEED * (-3.5 * ((slope + 0.05).abs() - 0.05)).exp();
+}
+
+/// elevation difference per length of a way in percent, negative downhill. Ways without length are flat
+pub fn get_gradient_percent(way: &Way, nodes: &[Node]) -> f64 {
+    if way.distance <= 0.0 {
+        return 0.0;
+    }
+    let source_ele = nodes[way.source as usize].elevation;
+    let target_ele = nodes[way.target as usize].elevation;
+    // distances are in km, elevations in m
+    return (target_ele - source_ele) as f64 / (way.distance as f64 * 1000.0) * 100.0;
+}
+
+/// returns the distance in km or the travel time in h of a way with the gradient in percent, bicycles and walkers
+/// slow down uphill
+pub fn get_way_weight(way: &Way, travel_type: TravelType, use_distance: bool, gradient_percent: f64, rider: &Rider) -> f64 {
+    return if use_distance {
+        way.distance as f64
+    } else {
+        // only cars need a speed, ways without one count as 1 km/h for them and do not cap bicycles
+        let speed = match travel_type {
+            TravelType::Car if way.speed == 0 => 1.0,
+            TravelType::Car => way.speed as f64,
+            TravelType::Bicycle if way.speed == 0 => rider.get_speed(gradient_percent),
+            TravelType::Bicycle => rider.get_speed(gradient_percent).min(way.speed as f64),
+            TravelType::Foot => get_walking_speed(gradient_percent),
+        };
+        way.distance as f64 / speed
+    };
+}
+
+/// fill offset array
+pub fn fill_offset(ways: &[Way], offset: &mut [u32]) {
+    for way in ways {
+        offset[way.source as usize + 1] += 1;
+    }
+    for i in 1..offset.len() {
+        offset[i] += offset[i - 1];
+    }
+}
+
+/// fill offset array of the incoming ways
+pub fn fill_reverse_offset(ways: &[Way], reverse_offset: &mut [u32]) {
+    for way in ways {
+        reverse_offset[way.target as usize + 1] += 1;
+    }
+    for i in 1..reverse_offset.len() {
+        reverse_offset[i] += reverse_offset[i - 1];
+    }
+}
+
+/// returns the grid cell of given coordinates
+/// cells are floored, so negative coordinates (western/southern hemisphere) get their own cells
+pub fn get_grid_cell(latitude: f32, longitude: f32) -> (i32, i32) {
+    let lat_grid = (latitude * GRID_MULTIPLICATOR as f32).floor() as i32;
+    let lng_grid = (longitude * GRID_MULTIPLICATOR as f32).floor() as i32;
+    return (lat_grid, lng_grid);
+}
+
+/// get distance on earth surface using haversine formula
+pub fn calc_distance(lat_1: f32, long_1: f32, lat_2: f32, long_2: f32) -> f32 {
+    let r: f32 = 6371.0; // constant used for meters
+    let d_lat: f32 = (lat_2 - lat_1).to_radians();
+    let d_lon: f32 = (long_2 - long_1).to_radians();
+    let lat1: f32 = (lat_1).to_radians();
+    let lat2: f32 = (lat_2).to_radians();
+
+    let a: f32 = ((d_lat / 2.0).sin()) * ((d_lat / 2.0).sin())
+        + ((d_lon / 2.0).sin()) * ((d_lon / 2.0).sin()) * (lat1.cos()) * (lat2.cos());
+    let c: f32 = 2.0 * ((a.sqrt()).atan2((1.0 - a).sqrt()));
+    return r * c;
+}
@@ -0,0 +1,107 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Deref;
+use std::slice;
+use std::sync::Arc;
+
+use memmap::Mmap;
+
+/// fixed size record stored little endian in an fmi file
+///
+/// # Safety
+/// Implementors must be `#[repr(C)]`, `size_of::<Self>() == SIZE`, without padding and valid for every
+/// bit pattern, because memory mapped files are reinterpreted as slices of them.
+pub unsafe trait Record: Copy {
+    const SIZE: usize;
+    fn write_le(&self, buf: &mut Vec<u8>);
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+unsafe impl Record for u32 {
+    const SIZE: usize = 4;
+
+    fn write_le(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        return u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+}
+
+/// array of records, either owned or borrowed from a memory mapped fmi file
+pub enum Storage<T: Record> {
+    Owned(Vec<T>),
+    Mapped {
+        mmap: Arc<Mmap>,
+        /// byte position of the first record
+        start: usize,
+        len: usize,
+        marker: PhantomData<T>,
+    },
+}
+
+impl<T: Record> Storage<T> {
+    /// borrows `len` records beginning at byte `start` of the mapped file
+    pub fn mapped(mmap: &Arc<Mmap>, start: usize, len: usize) -> Result<Storage<T>, String> {
+        if mem::size_of::<T>() != T::SIZE || !start.is_multiple_of(mem::align_of::<T>()) {
+            return Err("misaligned section".to_string());
+        }
+        match len.checked_mul(T::SIZE).and_then(|size| size.checked_add(start)) {
+            Some(end) if end <= mmap.len() => (),
+            _ => return Err("section exceeds the file size".to_string()),
+        }
+        return Ok(Storage::Mapped {
+            mmap: mmap.clone(),
+            start,
+            len,
+            marker: PhantomData,
+        });
+    }
+
+    /// size of the records in bytes
+    pub fn byte_size(&self) -> usize {
+        return self.len() * T::SIZE;
+    }
+}
+
+impl<T: Record> Deref for Storage<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        return match self {
+            Storage::Owned(records) => records,
+            Storage::Mapped { mmap, start, len, .. } => unsafe {
+                // bounds and alignment were checked in Storage::mapped, mmap is page aligned
+                slice::from_raw_parts(mmap.as_ptr().add(*start) as *const T, *len)
+            },
+        };
+    }
+}
+
+impl<T: Record> From<Vec<T>> for Storage<T> {
+    fn from(records: Vec<T>) -> Self {
+        return Storage::Owned(records);
+    }
+}
+
+impl<T: Record> Clone for Storage<T> {
+    fn clone(&self) -> Self {
+        return match self {
+            Storage::Owned(records) => Storage::Owned(records.clone()),
+            Storage::Mapped { mmap, start, len, .. } => Storage::Mapped {
+                mmap: mmap.clone(),
+                start: *start,
+                len: *len,
+                marker: PhantomData,
+            },
+        };
+    }
+}
+
+impl<T: Record + fmt::Debug> fmt::Debug for Storage<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f.debug_list().entries(self.iter()).finish();
+    }
+}
@@ -0,0 +1,448 @@
+use std::fs::File;
+use std::io::Write;
+
+use super::*;
+
+#[test]
+#[allow(clippy::vec_init_then_push)]
+fn fill_offset_test() {
+    let mut offset_test = vec![0; 8];
+    let mut ways = Vec::<Way>::new();
+    ways.push(Way {
+        source: 0,
+        target: 1,
+        speed: 2,
+        distance: 2.0,
+        travel_type: 1,
+        surface: 0,
+    });
+    ways.push(Way {
+        source: 0,
+        target: 1,
+        speed: 2,
+        distance: 2.0,
+        travel_type: 1,
+        surface: 0,
+    });
+    ways.push(Way {
+        source: 0,
+        target: 1,
+        speed: 2,
+        distance: 2.0,
+        travel_type: 1,
+        surface: 0,
+    });
+    ways.push(Way {
+        source: 2,
+        target: 1,
+        speed: 2,
+        distance: 2.0,
+        travel_type: 1,
+        surface: 0,
+    });
+    ways.push(Way {
+        source: 3,
+        target: 1,
+        speed: 2,
+        distance: 2.0,
+        travel_type: 1,
+        surface: 0,
+    });
+    ways.push(Way {
+        source: 4,
+        target: 1,
+        speed: 2,
+        distance: 2.0,
+        travel_type: 1,
+        surface: 0,
+    });
+    ways.push(Way {
+        source: 4,
+        target: 1,
+        speed: 2,
+        distance: 2.0,
+        travel_type: 1,
+        surface: 0,
+    });
+    ways.push(Way {
+        source: 4,
+        target: 1,
+        speed: 2,
+        distance: 2.0,
+        travel_type: 1,
+        surface: 0,
+    });
+    ways.push(Way {
+        source: 6,
+        target: 1,
+        speed: 2,
+        distance: 2.0,
+        travel_type: 1,
+        surface: 0,
+    });
+    fill_offset(&ways, &mut offset_test);
+
+    println!("{:?}", offset_test);
+
+    assert_eq!(offset_test[0], 0);
+    assert_eq!(offset_test[1], 3);
+    assert_eq!(offset_test[2], 3);
+    assert_eq!(offset_test[3], 4);
+    assert_eq!(offset_test[4], 5);
+    assert_eq!(offset_test[5], 8);
+    assert_eq!(offset_test[6], 8);
+    assert_eq!(offset_test[7], 9);
+}
+
+#[test]
+fn grid_cell_test() {
+    assert_eq!(get_grid_cell(48.7758, 9.1829), (4877, 918));
+    // western and southern hemisphere get negative cells instead of being clamped to 0
+    assert_eq!(get_grid_cell(40.4168, -3.7038), (4041, -371));
+    assert_eq!(get_grid_cell(-34.6037, -58.3816), (-3461, -5839));
+    assert_eq!(get_grid_cell(-0.001, 0.001), (-1, 0));
+    assert_eq!(get_grid_cell(0.001, -0.001), (0, -1));
+}
+
+#[test]
+fn header_test() {
+    let nodes = vec![
+        Node {
+            latitude: 48.7,
+            longitude: 9.1,
+            elevation: 0.0,
+        },
+        Node {
+            latitude: 48.9,
+            longitude: 8.9,
+            elevation: 0.0,
+        },
+    ];
+    let bounding_box = BoundingBox::from_nodes(&nodes);
+    assert_eq!((bounding_box.min_latitude, bounding_box.max_latitude), (48.7, 48.9));
+    assert_eq!((bounding_box.min_longitude, bounding_box.max_longitude), (8.9, 9.1));
+
+    let source = std::env::temp_dir().join("osm-dijkstra-fmi-header.osm.pbf");
+    File::create(&source).unwrap().write_all(b"123456789").unwrap();
+    let header = Header::new(&source, &nodes).unwrap();
+    assert_eq!(header.source_name, "osm-dijkstra-fmi-header.osm.pbf");
+    // crc32 check value
+    assert_eq!(header.source_checksum, 0xCBF4_3926);
+
+    let mut written = Vec::<u8>::new();
+    header.write_to(&mut written).unwrap();
+    assert_eq!(&written[..8], &MAGIC_NUMBER);
+    assert_eq!(&written[8..12], &FORMAT_VERSION.to_le_bytes());
+}
+
+#[test]
+fn read_header_test() {
+    let mut file = MAGIC_NUMBER.to_vec();
+    file.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    // created_at, source_name, bounding_box, source_checksum
+    file.extend_from_slice(&1_577_836_800u64.to_le_bytes());
+    file.extend_from_slice(&(9u64).to_le_bytes());
+    file.extend_from_slice(b"a.osm.pbf");
+    for value in [47.0f32, 9.0, 48.0, 10.0].iter() {
+        file.extend_from_slice(&value.to_le_bytes());
+    }
+    file.extend_from_slice(&0xCBF4_3926u32.to_le_bytes());
+    let header = Header::read_from(&mut file.as_slice()).unwrap();
+    assert_eq!(header.created_at, 1_577_836_800);
+    assert_eq!(header.source_name, "a.osm.pbf");
+    assert_eq!(header.bounding_box.max_longitude, 10.0);
+    assert_eq!(header.source_checksum, 0xCBF4_3926);
+
+    // bare bincode dump of an old version
+    let error = Header::read_from(&mut [0u8; 16].as_ref()).unwrap_err();
+    assert!(error.contains("magic number"));
+
+    let mut newer = MAGIC_NUMBER.to_vec();
+    newer.extend_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+    let error = Header::read_from(&mut newer.as_slice()).unwrap_err();
+    assert!(error.contains("not supported"));
+
+    assert!(Header::read_from(&mut b"OSM".as_ref()).is_err());
+}
+
+#[test]
+fn map_data_test() {
+    let node = |latitude, longitude| Node {
+        latitude,
+        longitude,
+        elevation: 0.0,
+    };
+    let way = |source, target| Way {
+        source,
+        target,
+        speed: 30,
+        distance: 1.0,
+        travel_type: 5,
+        surface: 0,
+    };
+    let map_data = MapData::new(
+        vec![node(48.775, 9.182), node(48.776, 9.183), node(-34.605, -58.385)],
+        vec![way(2, 0), way(0, 1), way(1, 0), way(0, 2)],
+    );
+    assert_eq!(&map_data.offset[..], &[0, 2, 3, 4]);
+    assert!(map_data.ways.windows(2).all(|w| w[0].source <= w[1].source));
+    assert_eq!(&map_data.reverse_way_ids[..], &[2, 3, 0, 1]);
+    assert_eq!(&map_data.reverse_offset[..], &[0, 2, 3, 4]);
+    assert_eq!(map_data.grid.get(&(4877, 918)), Some(&[0, 1][..]));
+    assert_eq!(map_data.grid.get(&(-3461, -5839)), Some(&[2][..]));
+    assert_eq!(map_data.grid.get(&(4877, 919)), None);
+    assert_eq!(map_data.grid.cells.len(), 2);
+}
+
+#[test]
+fn write_and_read_fmi_test() {
+    let source = std::env::temp_dir().join("osm-dijkstra-fmi-roundtrip.osm.pbf");
+    File::create(&source).unwrap().write_all(b"not really a pbf").unwrap();
+    let nodes = vec![
+        Node {
+            latitude: 40.4168,
+            longitude: -3.7038,
+            elevation: 650.0,
+        },
+        Node {
+            latitude: 40.4170,
+            longitude: -3.7040,
+            elevation: 655.5,
+        },
+    ];
+    let ways = vec![Way {
+        source: 1,
+        target: 0,
+        speed: 30,
+        distance: calc_distance(40.4170, -3.7040, 40.4168, -3.7038),
+        travel_type: 5,
+        surface: Surface::Compacted as u32,
+    }];
+    let header = Header::new(&source, &nodes).unwrap();
+    let map_data = MapData::new(nodes, ways);
+
+    let mut file = Vec::<u8>::new();
+    write_fmi(&mut file, &header, &map_data).unwrap();
+    let (read_header, read_map_data) = read_fmi(&mut file.as_slice()).unwrap();
+
+    assert_eq!(read_header.source_checksum, header.source_checksum);
+    assert_eq!(read_header.bounding_box, header.bounding_box);
+    assert_eq!(read_map_data.nodes[1].elevation, 655.5);
+    assert_eq!(read_map_data.ways[0].distance, map_data.ways[0].distance);
+    assert_eq!(Surface::from_u32(read_map_data.ways[0].surface), Surface::Compacted);
+    assert_eq!(&read_map_data.offset[..], &map_data.offset[..]);
+    assert_eq!(&read_map_data.reverse_way_ids[..], &map_data.reverse_way_ids[..]);
+    assert_eq!(&read_map_data.reverse_offset[..], &map_data.reverse_offset[..]);
+    assert_eq!(&read_map_data.grid.cells[..], &map_data.grid.cells[..]);
+    assert_eq!(&read_map_data.grid.node_ids[..], &map_data.grid.node_ids[..]);
+
+    // a truncated file must result in an error instead of a panic
+    assert!(read_fmi(&mut &file[..file.len() - 12]).is_err());
+
+    let path = std::env::temp_dir().join("osm-dijkstra-fmi-roundtrip.fmi");
+    File::create(&path).unwrap().write_all(&file).unwrap();
+    let (mapped_header, mapped_map_data) = map_fmi(&path).unwrap();
+    assert_eq!(mapped_header.source_name, header.source_name);
+    match mapped_map_data.nodes {
+        Storage::Mapped { .. } => (),
+        Storage::Owned(_) => panic!("nodes were copied instead of mapped"),
+    }
+    assert_eq!(mapped_map_data.nodes[0].latitude, 40.4168);
+    assert_eq!(mapped_map_data.nodes[1].elevation, 655.5);
+    assert_eq!(mapped_map_data.ways[0].source, 1);
+    assert_eq!(mapped_map_data.ways[0].distance, map_data.ways[0].distance);
+    assert_eq!(mapped_map_data.grid.get(&(4041, -371)), map_data.grid.get(&(4041, -371)));
+    assert_eq!(mapped_map_data.byte_size(), map_data.byte_size());
+
+    File::create(&path).unwrap().write_all(&file[..file.len() - 12]).unwrap();
+    assert!(map_fmi(&path).is_err());
+
+    // indices outside of the graph are rejected when loading instead of panicking in a search
+    let way = map_data.ways[0];
+    let corrupt_maps = vec![
+        MapData { ways: vec![Way { target: 2, ..way }].into(), ..map_data.clone() },
+        MapData { offset: vec![0, 2, 1].into(), ..map_data.clone() },
+        MapData { reverse_way_ids: vec![1].into(), ..map_data.clone() },
+        MapData { grid: Grid { cells: map_data.grid.cells.clone(), node_ids: vec![0, 5].into() }, ..map_data.clone() },
+    ];
+    for corrupt in corrupt_maps {
+        let mut file = Vec::<u8>::new();
+        write_fmi(&mut file, &header, &corrupt).unwrap();
+        assert!(read_fmi(&mut file.as_slice()).is_err());
+        File::create(&path).unwrap().write_all(&file).unwrap();
+        assert!(map_fmi(&path).is_err());
+    }
+}
+
+#[test]
+fn calc_distance_test() {
+    // stuttgart to hamburg is roughly 534km
+    let distance = calc_distance(48.7758, 9.1829, 53.5511, 9.9937);
+    assert!((distance - 534.0).abs() < 2.0);
+    assert_eq!(calc_distance(-34.6, -58.38, -34.6, -58.38), 0.0);
+}
+
+#[test]
+fn travel_type_test() {
+    for &travel_type in TravelType::ALL.iter() {
+        assert_eq!(TravelType::from_name(travel_type.name()), Some(travel_type));
+        assert_eq!(TravelType::from_u32(travel_type as u32), Some(travel_type));
+    }
+    assert_eq!(TravelType::from_name("horse"), None);
+    assert_eq!(TravelType::from_u32(3), None);
+
+    let allowed = |travel_type: TravelType| (0..7).filter(|&road_class| {
+        let way = Way {
+            source: 0,
+            target: 1,
+            speed: 50,
+            distance: 1.0,
+            travel_type: road_class,
+            surface: 0,
+        };
+        is_way_allowed(&way, travel_type)
+    }).collect::<Vec<u32>>();
+    assert_eq!(allowed(TravelType::Car), vec![0, 1, 5]);
+    assert_eq!(allowed(TravelType::Bicycle), vec![1, 2, 3, 5]);
+    assert_eq!(allowed(TravelType::Foot), vec![3, 4, 5]);
+}
+
+#[test]
+fn speed_model_test() {
+    let rider = Rider::DEFAULT;
+    let flat = rider.get_speed(0.0);
+    assert!(flat > 19.0 && flat < 21.0, "{}", flat);
+    // the power balances the losses at the speed
+    let power = |gradient_percent: f64| {
+        let v = rider.get_speed(gradient_percent) / 3.6;
+        let slope = gradient_percent / 100.0;
+        let hypotenuse = (1.0 + slope * slope).sqrt();
+        (rider.mass * 9.81 * (slope + 0.007) / hypotenuse + 0.5 * 1.2 * 0.45 * v * v) * v
+    };
+    for &gradient_percent in &[-3.0, -1.0, 0.0, 2.0, 10.0, 25.0] {
+        assert!((power(gradient_percent) - rider.power).abs() < 1e-6, "{}", gradient_percent);
+    }
+    assert!(rider.get_speed(10.0) < 8.0);
+    assert!(rider.get_speed(2.0) < flat && rider.get_speed(-2.0) > flat);
+    assert_eq!(rider.get_speed(-10.0), MAX_BICYCLE_SPEED);
+    let strong = Rider {
+        power: 250.0,
+        ..rider
+    };
+    assert!(strong.get_speed(5.0) > 2.0 * rider.get_speed(5.0));
+
+    assert!((get_walking_speed(0.0) - WALKING_SPEED).abs() < 1e-9);
+    assert!(get_walking_speed(-5.0) > WALKING_SPEED);
+    assert!(get_walking_speed(-20.0) < WALKING_SPEED);
+    assert!(get_walking_speed(20.0) < get_walking_speed(-20.0));
+
+    let way = Way {
+        source: 0,
+        target: 1,
+        speed: 50,
+        distance: 0.5,
+        travel_type: 5,
+        surface: 0,
+    };
+    assert_eq!(get_way_weight(&way, TravelType::Car, false, 8.0, &rider), 0.01);
+    assert_eq!(get_way_weight(&way, TravelType::Bicycle, true, 8.0, &rider), 0.5);
+    assert_eq!(get_way_weight(&way, TravelType::Foot, false, 8.0, &rider), 0.5 / get_walking_speed(8.0));
+    assert_eq!(get_way_weight(&Way { speed: 10, ..way }, TravelType::Bicycle, false, 0.0, &rider), 0.05);
+    // ways without speed keep the gradient model of bicycles and walkers
+    let unlimited = Way { speed: 0, ..way };
+    assert_eq!(get_way_weight(&unlimited, TravelType::Car, false, 8.0, &rider), 0.5);
+    assert_eq!(get_way_weight(&unlimited, TravelType::Bicycle, false, 8.0, &rider), 0.5 / rider.get_speed(8.0));
+    assert_eq!(get_way_weight(&unlimited, TravelType::Bicycle, false, -10.0, &rider), 0.5 / MAX_BICYCLE_SPEED);
+    assert_eq!(get_way_weight(&unlimited, TravelType::Foot, false, 8.0, &rider), 0.5 / get_walking_speed(8.0));
+    assert!(get_way_weight(&unlimited, TravelType::Bicycle, false, 8.0, &rider) > get_way_weight(&unlimited, TravelType::Bicycle, false, 0.0, &rider));
+}
+
+#[test]
+fn contraction_hierarchy_test() {
+    // 4x4 grid with ways in both directions, the last column is for pedestrians only
+    let mut nodes = Vec::new();
+    let mut ways = Vec::new();
+    for row in 0..4u32 {
+        for column in 0..4u32 {
+            nodes.push(Node {
+                latitude: 48.0 + row as f32 * 0.01,
+                longitude: 9.0 + column as f32 * 0.01,
+                elevation: 0.0,
+            });
+            let id = row * 4 + column;
+            let mut neighbors = vec![];
+            if column < 3 {
+                neighbors.push((id + 1, if column == 2 { 4 } else { 5 }));
+            }
+            if row < 3 {
+                neighbors.push((id + 4, if column == 3 { 4 } else { 5 }));
+            }
+            for (neighbor, travel_type) in neighbors {
+                for &(source, target) in &[(id, neighbor), (neighbor, id)] {
+                    ways.push(Way {
+                        source,
+                        target,
+                        speed: 30,
+                        distance: 1.0,
+                        travel_type,
+                        surface: 0,
+                    });
+                }
+            }
+        }
+    }
+    let mut map_data = MapData::new(nodes, ways);
+    let ch = ContractionHierarchy::new(&map_data, TravelType::Car, true);
+
+    let mut ranks = ch.ranks.to_vec();
+    ranks.sort();
+    assert_eq!(ranks, (0..16).collect::<Vec<u32>>());
+    for node in 0..16 {
+        for edge in &ch.up[ch.up_offset[node] as usize..ch.up_offset[node + 1] as usize] {
+            assert!(ch.ranks[edge.neighbor as usize] > ch.ranks[node]);
+        }
+        for edge in &ch.down[ch.down_offset[node] as usize..ch.down_offset[node + 1] as usize] {
+            assert!(ch.ranks[edge.neighbor as usize] > ch.ranks[node]);
+        }
+    }
+    // cars can not reach the last column, so no edge may lead there
+    for edge in ch.up.iter().chain(ch.down.iter()) {
+        assert_ne!(edge.neighbor % 4, 3);
+        assert!(edge.middle == NO_MIDDLE || edge.middle % 4 != 3);
+    }
+    // every edge unpacks into a path of original ways with the same weight
+    for node in 0..16 {
+        for edge in &ch.up[ch.up_offset[node] as usize..ch.up_offset[node + 1] as usize] {
+            let mut path = Vec::new();
+            ch.unpack(node, edge.neighbor as usize, &mut path);
+            assert_eq!(path.len() as f64 - 1.0, edge.weight);
+            assert_eq!((path[0], *path.last().unwrap()), (node, edge.neighbor as usize));
+        }
+    }
+
+    map_data.hierarchies.push(ch);
+    map_data.hierarchies.push(ContractionHierarchy::new(&map_data, TravelType::Foot, false));
+    let header = Header {
+        created_at: 0,
+        source_name: "grid".to_string(),
+        bounding_box: BoundingBox::from_nodes(&map_data.nodes),
+        source_checksum: 0,
+    };
+    let mut file = Vec::<u8>::new();
+    write_fmi(&mut file, &header, &map_data).unwrap();
+    let (_, read_map_data) = read_fmi(&mut file.as_slice()).unwrap();
+    let path = std::env::temp_dir().join("osm-dijkstra-fmi-ch.fmi");
+    File::create(&path).unwrap().write_all(&file).unwrap();
+    let (_, mapped_map_data) = map_fmi(&path).unwrap();
+    for loaded in &[read_map_data, mapped_map_data] {
+        assert_eq!(loaded.hierarchies.len(), 2);
+        for (loaded_ch, ch) in loaded.hierarchies.iter().zip(map_data.hierarchies.iter()) {
+            assert_eq!((loaded_ch.travel_type, loaded_ch.by_distance), (ch.travel_type, ch.by_distance));
+            assert_eq!(&loaded_ch.ranks[..], &ch.ranks[..]);
+            assert_eq!(&loaded_ch.up[..], &ch.up[..]);
+            assert_eq!(&loaded_ch.up_offset[..], &ch.up_offset[..]);
+            assert_eq!(&loaded_ch.down[..], &ch.down[..]);
+            assert_eq!(&loaded_ch.down_offset[..], &ch.down_offset[..]);
+        }
+    }
+}
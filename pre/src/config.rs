@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+use clap::{App, Arg};
+use fmi::BoundingBox;
+
+use srtm::{DEFAULT_SRTM_FOLDER, DEFAULT_SRTM_URL, Interpolation};
+
+/// parses a bounding box given as min_lon,min_lat,max_lon,max_lat (same order as `osmium extract --bbox`)
+pub fn parse_bounding_box(s: &str) -> Result<BoundingBox, String> {
+    let values = s
+        .split(',')
+        .map(|v| v.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|_e| format!("bounding box '{}' contains an invalid number", s))?;
+    if values.len() != 4 {
+        return Err(format!("bounding box '{}' needs four values: min_lon,min_lat,max_lon,max_lat", s));
+    }
+    let bounding_box = BoundingBox {
+        min_longitude: values[0],
+        min_latitude: values[1],
+        max_longitude: values[2],
+        max_latitude: values[3],
+    };
+    if bounding_box.min_latitude > bounding_box.max_latitude || bounding_box.min_longitude > bounding_box.max_longitude {
+        return Err(format!("bounding box '{}' has min values above its max values", s));
+    }
+    return Ok(bounding_box);
+}
+
+pub struct Config {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub srtm_folder: PathBuf,
+    pub srtm_url: String,
+    pub offline: bool,
+    pub bounding_box: Option<BoundingBox>,
+    pub interpolation: Interpolation,
+    pub contraction_hierarchies: bool,
+}
+
+impl Config {
+    /// parses the command line arguments, exits with a usage message if they are invalid
+    pub fn from_args() -> Config {
+        let matches = App::new("osm-dijkstra-pre")
+            .version(env!("CARGO_PKG_VERSION"))
+            .about("Converts an *.osm.pbf file into a *.fmi graph with SRTM elevations")
+            .arg(Arg::with_name("input")
+                .help("osm pbf file to parse")
+                .required(true)
+                .index(1))
+            .arg(Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .help("where the graph is written to [default: <input>.fmi]"))
+            .arg(Arg::with_name("srtm-dir")
+                .long("srtm-dir")
+                .value_name("DIR")
+                .default_value(DEFAULT_SRTM_FOLDER)
+                .help("directory containing the *.hgt tiles, missing tiles are downloaded into it"))
+            .arg(Arg::with_name("srtm-url")
+                .long("srtm-url")
+                .value_name("URL")
+                .default_value(DEFAULT_SRTM_URL)
+                .help("server the missing tiles are downloaded from, below it in Eurasia, Africa, North_America, South_America, Australia or Islands"))
+            .arg(Arg::with_name("offline")
+                .long("offline")
+                .help("never download srtm tiles, fail if a tile is missing in --srtm-dir"))
+            .arg(Arg::with_name("bbox")
+                .long("bbox")
+                .value_name("MIN_LON,MIN_LAT,MAX_LON,MAX_LAT")
+                .allow_hyphen_values(true)
+                .help("only keep nodes inside this bounding box and the ways between them"))
+            .arg(Arg::with_name("interpolation")
+                .long("interpolation")
+                .value_name("METHOD")
+                .possible_values(&["none", "idw", "bilinear"])
+                .default_value("idw")
+                .help("how node elevations are interpolated from the srtm samples"))
+            .arg(Arg::with_name("ch")
+                .long("ch")
+                .help("also create contraction hierarchies for fast queries without elevation limit, takes much longer"))
+            .get_matches();
+
+        let input = PathBuf::from(matches.value_of_os("input").unwrap());
+        let output = match matches.value_of_os("output") {
+            Some(output) => PathBuf::from(output),
+            None => {
+                let mut output = input.clone().into_os_string();
+                output.push(".fmi");
+                PathBuf::from(output)
+            }
+        };
+        let bounding_box = match matches.value_of("bbox") {
+            Some(bbox) => match parse_bounding_box(bbox) {
+                Ok(bounding_box) => Some(bounding_box),
+                Err(e) => clap::Error::with_description(&e, clap::ErrorKind::InvalidValue).exit(),
+            },
+            None => None,
+        };
+        return Config {
+            input,
+            output,
+            srtm_folder: PathBuf::from(matches.value_of_os("srtm-dir").unwrap()),
+            srtm_url: matches.value_of("srtm-url").unwrap().to_string(),
+            offline: matches.is_present("offline"),
+            bounding_box,
+            interpolation: Interpolation::from_name(matches.value_of("interpolation").unwrap()).unwrap(),
+            contraction_hierarchies: matches.is_present("ch"),
+        };
+    }
+}
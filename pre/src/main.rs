@@ -1,4 +1,8 @@
-extern crate bincode;
+// explicit returns are the code style of this project
+#![allow(clippy::needless_return)]
+
+extern crate clap;
+extern crate fmi;
 extern crate osmpbfreader;
 extern crate serde;
 extern crate serde_json;
@@ -6,45 +10,19 @@ extern crate serde_json;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufWriter;
-use std::path::Path;
+use std::time::Instant;
 
-use bincode::serialize_into;
+use fmi::{calc_distance, ContractionHierarchy, Header, MapData, Node, RoadClass, Surface, TravelType, Way, write_fmi};
 use osmpbfreader::{groups, primitive_block_from_blob};
-use serde::Serialize;
 
+use config::Config;
 use srtm::SRTM;
 
 #[cfg(test)]
 mod tests;
+mod config;
 mod srtm;
 
-// First three digits of coordinates are used for the grid hashing
-const GRID_MULTIPLICATOR: usize = 100;
-
-#[derive(Serialize, Debug, Clone)]
-struct Way {
-    source: usize,
-    target: usize,
-    speed: usize,
-    distance: f32,
-    travel_type: usize,
-}
-
-#[derive(Serialize, Debug, Clone)]
-struct Node {
-    latitude: f32,
-    longitude: f32,
-    elevation: f32,
-}
-
-#[derive(Serialize, Debug)]
-struct Output {
-    nodes: Vec<Node>,
-    ways: Vec<Way>,
-    offset: Vec<usize>,
-    grid: HashMap<(usize, usize), Vec<usize>>,
-}
-
 fn parse_speed(max_speed: &str, highway: &str) -> usize {
     match max_speed.parse::<usize>() {
         Ok(ok) => return ok,
@@ -109,68 +87,63 @@ fn aproximate_speed_limit(s: &str) -> usize {
 }
 
 /// get what kind of street it is:
-/* infos from https://wiki.openstreetmap.org/wiki/Key:highway
-0 = car only
-1 = car and bicycle
-2 = bicycle
-3 = bicycle and pedestrian
-4 = pedestrian
-5 = all
-100 = skip
-*/
-fn get_street_type(s: &str, has_sidewalk: bool) -> usize {
-    let mut result = match s {
-        "motorway" | "motorway_link" => 0,
-        "trunk" | "trunk_link" => 0,
-        "raceway" | "services" | "rest_area" => 0,
-        "primary" | "primary_link" => 1,
-        "secondary" | "secondary_link" => 1,
-        "tertiary" | "tertiary_link" => 1,
-        "cycleway" => 2,
-        "trail" | "track" | "path" => 3,
-        "elevator" | "platform" | "corridor" => 4,
-        "bus_stop" | "bridleway" | "steps" | "pedestrian" | "footway" => 4,
-        "unclassified" => 5,
-        "residential" | "living_street" => 5,
-        "service" | "road" => 5,
-        "razed" | "abandoned" | "disused" | "construction" | "proposed" => 100,
-        _ => 5,
+/// road class of a highway tag, None for ways that can not be used at all
+/// infos from https://wiki.openstreetmap.org/wiki/Key:highway
+fn get_road_class(s: &str, has_sidewalk: bool) -> Option<RoadClass> {
+    let result = match s {
+        "motorway" | "motorway_link" => RoadClass::CarOnly,
+        "trunk" | "trunk_link" => RoadClass::CarOnly,
+        "raceway" | "services" | "rest_area" => RoadClass::CarOnly,
+        "primary" | "primary_link" => RoadClass::CarAndBicycle,
+        "secondary" | "secondary_link" => RoadClass::CarAndBicycle,
+        "tertiary" | "tertiary_link" => RoadClass::CarAndBicycle,
+        "cycleway" => RoadClass::Bicycle,
+        "trail" | "track" | "path" => RoadClass::BicycleAndFoot,
+        "elevator" | "platform" | "corridor" => RoadClass::Foot,
+        "bus_stop" | "bridleway" | "steps" | "pedestrian" | "footway" => RoadClass::Foot,
+        "unclassified" => RoadClass::All,
+        "residential" | "living_street" => RoadClass::All,
+        "service" | "road" => RoadClass::All,
+        "razed" | "abandoned" | "disused" | "construction" | "proposed" => return None,
+        _ => RoadClass::All,
     };
     if has_sidewalk {
-        result = match result {
-            1 => 5,
-            2 => 3,
-            3 => 5,
+        return Some(match result {
+            RoadClass::CarAndBicycle => RoadClass::All,
+            RoadClass::Bicycle => RoadClass::BicycleAndFoot,
+            RoadClass::BicycleAndFoot => RoadClass::All,
             _ => result,
-        }
+        });
     }
-    return result;
+    return Some(result);
+}
+
+/// surface class of a surface tag
+/// infos from https://wiki.openstreetmap.org/wiki/Key:surface
+fn get_surface(s: &str) -> Surface {
+    return match s {
+        "asphalt" | "concrete" | "concrete:plates" | "concrete:lanes" | "paved" | "paving_stones" | "metal" | "wood" => Surface::Paved,
+        "sett" | "cobblestone" | "unhewn_cobblestone" | "cobblestone:flattened" | "grass_paver" => Surface::Cobblestone,
+        "compacted" | "fine_gravel" => Surface::Compacted,
+        "unpaved" | "gravel" | "pebblestone" | "rock" | "dirt" | "earth" | "ground" | "grass" | "mud" | "sand" | "woodchips" => Surface::Unpaved,
+        _ => Surface::Unknown,
+    };
 }
 
 fn main() {
     let mut ways = Vec::<Way>::new();
     let mut nodes = Vec::<Node>::new();
-    let mut offset = Vec::<usize>::new();
-    // stores node ids for a 2d grid e.g. (1,1) = [1,2,3,..]
-    let mut grid = HashMap::<(usize, usize), Vec<usize>>::new();
 
     let mut amount_nodes = 0;
 
-    // check if arguments are right
-    let args: Vec<_> = std::env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: {} pbf_file", args[0]);
-        return;
-    }
+    let config = Config::from_args();
 
     // read pbf file
-    let filename = std::env::args_os().nth(1).unwrap();
-    let path = Path::new(&filename);
-    if !path.exists() {
-        println!("{} not found", filename.into_string().unwrap());
+    if !config.input.exists() {
+        println!("{} not found", config.input.display());
         std::process::exit(1);
     }
-    let r = File::open(&path).unwrap();
+    let r = File::open(&config.input).unwrap();
     let mut pbf = osmpbfreader::OsmPbfReader::new(r);
 
     // for storing mapping of own-ids and osm-ids
@@ -180,25 +153,26 @@ fn main() {
     for block in pbf.blobs().map(|b| primitive_block_from_blob(&b.unwrap())) {
         let block = block.unwrap();
         for group in block.get_primitivegroup().iter() {
-            for way in groups::ways(&group, &block) {
+            for way in groups::ways(group, &block) {
                 if way.tags.contains_key("highway") {
                     let highway = way.tags.get("highway").unwrap().trim();
                     let mut has_sidewalk: bool = false;
                     if way.tags.contains_key("sidewalk") {
-                        has_sidewalk = match way.tags.get("sidewalk").unwrap().trim() {
-                            "None" | "none" | "No" | "no" => false,
-                            _ => true,
-                        }
-                    }
-                    let travel_type = get_street_type(highway, has_sidewalk);
-                    if travel_type == 100 {
-                        continue;
+                        has_sidewalk = !matches!(way.tags.get("sidewalk").unwrap().trim(), "None" | "none" | "No" | "no");
                     }
+                    let road_class = match get_road_class(highway, has_sidewalk) {
+                        Some(road_class) => road_class,
+                        None => continue,
+                    };
                     let mut max_speed: &str = "";
                     if way.tags.contains_key("maxspeed") {
                         max_speed = way.tags.get("maxspeed").unwrap().trim();
                     }
                     let speed = parse_speed(max_speed, highway);
+                    let mut surface = Surface::Unknown;
+                    if way.tags.contains_key("surface") {
+                        surface = get_surface(way.tags.get("surface").unwrap().trim());
+                    }
 
                     let mut one_way: &str = "";
                     if way.tags.contains_key("oneway") {
@@ -207,42 +181,28 @@ fn main() {
                     let (one_way, reverse_dir): (bool, bool) = parse_one_way(one_way);
 
                     // get all node IDs from ways without duplication
-                    let mut prev_id: usize;
-                    let osm_id = way.nodes[0].0;
-                    if osm_id_mapping.contains_key(&osm_id) {
-                        prev_id = *osm_id_mapping.get(&osm_id).unwrap();
-                    } else {
-                        osm_id_mapping.insert(osm_id, amount_nodes);
-                        prev_id = amount_nodes;
-                        amount_nodes += 1;
-                    }
+                    let mut prev_id = get_or_insert_node_id(&mut osm_id_mapping, way.nodes[0].0, &mut amount_nodes);
                     // iterate over nodes and add them
                     for node in way.nodes.iter().skip(1) {
-                        let osm_id = node.0;
-                        let id;
-                        if osm_id_mapping.contains_key(&osm_id) {
-                            id = *osm_id_mapping.get(&osm_id).unwrap();
-                        } else {
-                            osm_id_mapping.insert(osm_id, amount_nodes);
-                            id = amount_nodes;
-                            amount_nodes += 1;
-                        }
-                        if (!reverse_dir && one_way) || !one_way {
+                        let id = get_or_insert_node_id(&mut osm_id_mapping, node.0, &mut amount_nodes);
+                        if !reverse_dir || !one_way {
                             ways.push(Way {
-                                source: prev_id,
-                                target: id,
-                                speed: speed,
+                                source: prev_id as u32,
+                                target: id as u32,
+                                speed: speed as u32,
                                 distance: 0.0,
-                                travel_type: travel_type,
+                                travel_type: road_class as u32,
+                                surface: surface as u32,
                             });
                         }
-                        if (reverse_dir && one_way) || !one_way {
+                        if reverse_dir || !one_way {
                             ways.push(Way {
-                                source: id,
-                                target: prev_id,
-                                speed: speed,
+                                source: id as u32,
+                                target: prev_id as u32,
+                                speed: speed as u32,
                                 distance: 0.0,
-                                travel_type: travel_type,
+                                travel_type: road_class as u32,
+                                surface: surface as u32,
                             });
                         }
                         prev_id = id;
@@ -252,7 +212,7 @@ fn main() {
         }
     }
 
-    // resize offset and nodes
+    // resize nodes
     nodes.resize(
         amount_nodes,
         Node {
@@ -261,7 +221,6 @@ fn main() {
             elevation: 0.0,
         },
     );
-    offset.resize(amount_nodes + 1, 0);
 
     // reset pbf reader
     match pbf.rewind() {
@@ -269,19 +228,33 @@ fn main() {
         Err(_e) => panic!("rewind was not successfull"),
     }
 
-    let mut srtm = SRTM::new();
+    let mut srtm = SRTM::new(&config.srtm_folder, &config.srtm_url, config.offline, config.interpolation);
+    // nodes that are missing in the pbf file or outside of the bounding box are removed afterwards
+    let mut is_valid_node = vec![false; amount_nodes];
     // store all geo-information about the nodes
     let mut latest_elevation_opt: Option<f32> = None;
     for block in pbf.blobs().map(|b| primitive_block_from_blob(&b.unwrap())) {
         let block = block.unwrap();
         for group in block.get_primitivegroup().iter() {
-            for node in groups::dense_nodes(&group, &block) {
+            for node in groups::dense_nodes(group, &block) {
                 // check if node in osm_id_mapping
                 match osm_id_mapping.get(&node.id.0) {
                     Some(our_id) => {
                         let latitude = node.decimicro_lat as f32 / 10_000_000.0;
                         let longitude = node.decimicro_lon as f32 / 10_000_000.0;
-                        let elevation = match srtm.get_elevation(latitude, longitude, true) {
+                        if let Some(bounding_box) = config.bounding_box {
+                            if !bounding_box.contains(latitude, longitude) {
+                                continue;
+                            }
+                        }
+                        let elevation_opt = match srtm.get_elevation(latitude, longitude) {
+                            Ok(elevation_opt) => elevation_opt,
+                            Err(e) => {
+                                println!("{}", e);
+                                std::process::exit(1);
+                            }
+                        };
+                        let elevation = match elevation_opt {
                             Some(elevation) => {
                                 latest_elevation_opt = Some(elevation);
                                 elevation
@@ -301,18 +274,7 @@ fn main() {
                             longitude,
                             elevation,
                         };
-                        let lat_grid = (latitude * GRID_MULTIPLICATOR as f32) as usize;
-                        let lng_grid = (longitude * GRID_MULTIPLICATOR as f32) as usize;
-                        match grid.get_mut(&(lat_grid, lng_grid)) {
-                            Some(id_list) => {
-                                id_list.push(*our_id);
-                            }
-                            None => {
-                                let mut new_id_list = Vec::<usize>::new();
-                                new_id_list.push(*our_id);
-                                grid.insert((lat_grid as usize, lng_grid as usize), new_id_list);
-                            }
-                        }
+                        is_valid_node[*our_id] = true;
                     }
                     None => continue,
                 }
@@ -320,60 +282,66 @@ fn main() {
         }
     }
 
-    ways.sort_by(|a, b| a.source.cmp(&b.source));
-    fill_offset(&ways, &mut offset);
+    remove_invalid_nodes(&mut nodes, &mut ways, &is_valid_node);
 
-    //let mut counter: usize = 0;
+    for way in ways.iter_mut() {
+        let source = nodes[way.source as usize];
+        let target = nodes[way.target as usize];
+        way.distance = calc_distance(source.latitude, source.longitude, target.latitude, target.longitude);
+    }
 
-    for i in 0..ways.len() {
-        ways[i].distance = calc_distance(
-            nodes[ways[i].source].latitude,
-            nodes[ways[i].source].longitude,
-            nodes[ways[i].target].latitude,
-            nodes[ways[i].target].longitude,
-        );
-        /*
-        if ways[i].distance == 0 {
-            counter += 1;
+    let header = Header::new(&config.input, &nodes).unwrap();
+    let mut result = MapData::new(nodes, ways);
+
+    if config.contraction_hierarchies {
+        for &travel_type in TravelType::ALL.iter() {
+            for &by_distance in &[true, false] {
+                let timing = Instant::now();
+                let ch = ContractionHierarchy::new(&result, travel_type, by_distance);
+                println!("contracted graph for travel type {} (by distance: {}) with {} edges in {:?}",
+                         travel_type.name(), by_distance, ch.up.len() + ch.down.len(), timing.elapsed());
+                result.hierarchies.push(ch);
+            }
         }
-        */
     }
-    //println!("zero counter {:?}", counter);
 
     // serialize everything
-    let result = Output {
-        nodes,
-        ways,
-        offset,
-        grid,
-    };
+    println!("everything gets written to {}", config.output.display());
+    let mut f = BufWriter::new(File::create(&config.output).unwrap());
+    write_fmi(&mut f, &header, &result).unwrap();
+}
 
-    let output_file = format!("{}{}", filename.into_string().unwrap(), ".fmi");
-    println!("everything gets written to {}", output_file);
-    let mut f = BufWriter::new(File::create(output_file).unwrap());
-    serialize_into(&mut f, &result).unwrap();
+/// returns our id for an osm node id, new ids are assigned in order of appearance
+fn get_or_insert_node_id(osm_id_mapping: &mut HashMap<i64, usize>, osm_id: i64, amount_nodes: &mut usize) -> usize {
+    return *osm_id_mapping.entry(osm_id).or_insert_with(|| {
+        *amount_nodes += 1;
+        *amount_nodes - 1
+    });
 }
 
-/// fill offset array
-fn fill_offset(ways: &Vec<Way>, offset: &mut Vec<usize>) {
-    for way in ways {
-        offset[way.source + 1] += 1;
+/// removes all nodes that are not flagged as valid and the ways using them, remaining nodes keep their order
+fn remove_invalid_nodes(nodes: &mut Vec<Node>, ways: &mut Vec<Way>, is_valid_node: &[bool]) {
+    let mut new_ids = Vec::<Option<u32>>::with_capacity(nodes.len());
+    let mut amount_valid = 0;
+    for &is_valid in is_valid_node {
+        if is_valid {
+            new_ids.push(Some(amount_valid));
+            amount_valid += 1;
+        } else {
+            new_ids.push(None);
+        }
     }
-    for i in 1..offset.len() {
-        offset[i] += offset[i - 1];
+    if amount_valid as usize == nodes.len() {
+        return;
+    }
+    let mut id = 0;
+    nodes.retain(|_node| {
+        id += 1;
+        is_valid_node[id - 1]
+    });
+    ways.retain(|way| is_valid_node[way.source as usize] && is_valid_node[way.target as usize]);
+    for way in ways.iter_mut() {
+        way.source = new_ids[way.source as usize].unwrap();
+        way.target = new_ids[way.target as usize].unwrap();
     }
-}
-
-/// get distance on earth surface using haversine formula
-fn calc_distance(lat_1: f32, long_1: f32, lat_2: f32, long_2: f32) -> f32 {
-    let r: f32 = 6371.0; // constant used for meters
-    let d_lat: f32 = (lat_2 - lat_1).to_radians();
-    let d_lon: f32 = (long_2 - long_1).to_radians();
-    let lat1: f32 = (lat_1).to_radians();
-    let lat2: f32 = (lat_2).to_radians();
-
-    let a: f32 = ((d_lat / 2.0).sin()) * ((d_lat / 2.0).sin())
-        + ((d_lon / 2.0).sin()) * ((d_lon / 2.0).sin()) * (lat1.cos()) * (lat2.cos());
-    let c: f32 = 2.0 * ((a.sqrt()).atan2((1.0 - a).sqrt()));
-    return r * c;
 }
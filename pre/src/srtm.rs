@@ -2,18 +2,46 @@ extern crate reqwest;
 extern crate zip;
 
 use std::collections::HashMap;
-use std::env::join_paths;
 use std::fs::File;
-use std::io::{Bytes, copy, Read, Write};
-use std::ops::Deref;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::fs;
 
 const ELEVATION_NULL_VALUE: i16 = -32768;
-const SRTM_FOLDER_NAME: &str = "../strm_files";
+pub const DEFAULT_SRTM_FOLDER: &str = "../strm_files";
+pub const DEFAULT_SRTM_URL: &str = "https://dds.cr.usgs.gov/srtm/version2_1/SRTM3";
+/// directories of the srtm3 download server, every tile is in one of them
+const REGIONS: [&str; 6] = ["Eurasia", "Africa", "North_America", "South_America", "Australia", "Islands"];
 
+#[allow(clippy::upper_case_acronyms)]
 pub struct SRTM {
     _files: HashMap<String, GeoElevationFile>,
+    folder: PathBuf,
+    url: String,
+    offline: bool,
+    interpolation: Interpolation,
+}
+
+/// how the elevation of a coordinate is derived from the surrounding srtm samples
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interpolation {
+    /// elevation of the nearest sample
+    None,
+    /// nearest sample and its four direct neighbors weighted by their inverse distance
+    InverseDistance,
+    /// bilinear interpolation between the four samples surrounding the coordinate
+    Bilinear,
+}
+
+impl Interpolation {
+    pub fn from_name(name: &str) -> Option<Interpolation> {
+        return match name {
+            "none" => Some(Interpolation::None),
+            "idw" => Some(Interpolation::InverseDistance),
+            "bilinear" => Some(Interpolation::Bilinear),
+            _ => None,
+        };
+    }
 }
 
 struct GeoElevationFile {
@@ -36,77 +64,114 @@ enum LongitudeOrientation {
 }
 
 impl SRTM {
-    pub fn new() -> Self {
+    /// srtm tiles are read from `folder` and missing ones downloaded from the regions below `url` into it,
+    /// downloads are refused if `offline` is set
+    pub fn new(folder: &Path, url: &str, offline: bool, interpolation: Interpolation) -> Self {
         SRTM {
-            _files: HashMap::<String, GeoElevationFile>::new()
+            _files: HashMap::<String, GeoElevationFile>::new(),
+            folder: folder.to_path_buf(),
+            url: url.trim_end_matches('/').to_string(),
+            offline,
+            interpolation,
         }
     }
 
-    pub fn get_elevation(&mut self, latitude: f32, longitude: f32, interpolate: bool) -> Option<f32> {
-        let geo_file = self.get_file(latitude, longitude);
-        if !interpolate {
-            return match geo_file.get_elevation(latitude, longitude) {
+    /// returns the elevation of the coordinate or an error if the srtm tile could not be loaded
+    pub fn get_elevation(&mut self, latitude: f32, longitude: f32) -> Result<Option<f32>, String> {
+        let interpolation = self.interpolation;
+        let geo_file = self.get_file(latitude, longitude)?;
+        return Ok(match interpolation {
+            Interpolation::None => match geo_file.get_elevation(latitude, longitude) {
                 Ok(elevation) => {
                     if elevation == ELEVATION_NULL_VALUE { None } else { Some(elevation as f32) }
                 }
-                Err(error) => None
-            };
-        } else {
-            return Some(geo_file.get_interpolated_elevation(latitude, longitude));
-        }
+                Err(_error) => None
+            },
+            Interpolation::InverseDistance => Some(geo_file.get_interpolated_elevation(latitude, longitude)),
+            Interpolation::Bilinear => geo_file.get_bilinear_elevation(latitude, longitude),
+        });
     }
 
-    fn get_file(&mut self, latitude: f32, longitude: f32) -> &GeoElevationFile {
+    fn get_file(&mut self, latitude: f32, longitude: f32) -> Result<&GeoElevationFile, String> {
         let file_name = SRTM::get_file_name(latitude, longitude);
         if !self._files.contains_key(file_name.as_str()) {
-            let data = self.load_file_data(file_name.as_str());
+            let data = self.load_file_data(file_name.as_str(), latitude, longitude)?;
             let geo_file = GeoElevationFile::new(file_name.clone(), latitude, longitude, data);
             self._files.insert(String::from(file_name.as_str()), geo_file);
         }
-        return self._files.get(file_name.as_str()).unwrap();
+        return Ok(self._files.get(file_name.as_str()).unwrap());
     }
 
-    fn load_file_data(&self, file_name: &str) -> Vec<u8> {
-        let srtm_folder: &Path = Path::new(SRTM_FOLDER_NAME);
-        if !srtm_folder.exists() {
-            fs::create_dir_all(srtm_folder);
+    fn load_file_data(&self, file_name: &str, latitude: f32, longitude: f32) -> Result<Vec<u8>, String> {
+        if !self.folder.exists() {
+            fs::create_dir_all(&self.folder).map_err(|err| format!("could not create {}: {}", self.folder.display(), err))?;
         }
-        let file_path: String = SRTM::get_relative_strm_file_path(file_name);
-        let path: &Path = Path::new(&file_path);
+        let path = self.get_strm_file_path(file_name);
         if !path.exists() {
+            if self.offline {
+                return Err(format!("{} not found locally and downloading is disabled", path.display()));
+            }
             println!("{} not found locally", path.display());
-            SRTM::download_srtm_file(file_name);
+            self.download_srtm_file(file_name, latitude, longitude).map_err(|err| format!("could not download {}: {}", file_name, err))?;
         }
-        let mut file: File = File::open(path).unwrap();
+        let mut file: File = File::open(&path).map_err(|err| format!("could not open {}: {}", path.display(), err))?;
         let mut buf = Vec::<u8>::new();
-        file.read_to_end(&mut buf);
-        buf
+        file.read_to_end(&mut buf).map_err(|err| format!("could not read {}: {}", path.display(), err))?;
+        Ok(buf)
     }
 
-    fn get_relative_strm_file_path(file_name: &str) -> String {
-        let path = format!("{}/{}.hgt", SRTM_FOLDER_NAME, file_name);
-        path
+    fn get_strm_file_path(&self, file_name: &str) -> PathBuf {
+        return self.folder.join(format!("{}.hgt", file_name));
     }
 
-    fn download_srtm_file(file_name: &str) -> Result<(), reqwest::Error> {
-        // download zip file into response reader
-        let url = format!("https://dds.cr.usgs.gov/srtm/version2_1/SRTM3/Eurasia/{}.hgt.zip", file_name);
-        println!("downloading srtm file from: {}", url);
-        let mut response = reqwest::blocking::get(url.as_str())?;
+    /// downloads and unzips the tile, the region of its coordinates is tried first and the others if it is not there
+    fn download_srtm_file(&self, file_name: &str, latitude: f32, longitude: f32) -> Result<(), String> {
+        let mut found = None;
+        for region in SRTM::get_regions(latitude, longitude).iter() {
+            let url = format!("{}/{}/{}.hgt.zip", self.url, region, file_name);
+            println!("downloading srtm file from: {}", url);
+            let response = reqwest::blocking::get(url.as_str()).map_err(|err| err.to_string())?;
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                continue;
+            }
+            found = Some(response.error_for_status().map_err(|err| err.to_string())?);
+            break;
+        }
+        let mut response = found.ok_or_else(|| format!("tile is in none of the regions of {}", self.url))?;
 
         // unzip file
-        let mut unzipped_file = zip::read::read_zipfile_from_stream(&mut response).unwrap().unwrap();
+        let mut unzipped_file = match zip::read::read_zipfile_from_stream(&mut response) {
+            Ok(Some(unzipped_file)) => unzipped_file,
+            Ok(None) => return Err("zip file is empty".to_string()),
+            Err(err) => return Err(format!("could not unzip: {}", err)),
+        };
         let mut file_buf: Vec<u8> = Vec::new();
-        unzipped_file.read_to_end(&mut file_buf).map_err(|err|println!("{:?}", err)).err();
+        unzipped_file.read_to_end(&mut file_buf).map_err(|err| format!("could not unzip: {}", err))?;
 
         // save unzipped file
-        let mut file_location = SRTM::get_relative_strm_file_path(file_name);
-        let mut dest = File::create(&file_location).unwrap();
-        dest.write_all(&file_buf).map_err(|err| println!("{:?}", err)).err();
-        println!("saved srtm file to: {}", file_location);
+        let file_location = self.get_strm_file_path(file_name);
+        let mut dest = File::create(&file_location).map_err(|err| format!("could not create {}: {}", file_location.display(), err))?;
+        dest.write_all(&file_buf).map_err(|err| format!("could not write {}: {}", file_location.display(), err))?;
+        println!("saved srtm file to: {}", file_location.display());
         Ok(())
     }
 
+    /// regions of the download server in the order they are tried, the one the coordinates most likely belong to first
+    fn get_regions(latitude: f32, longitude: f32) -> Vec<&'static str> {
+        let likely = if longitude < -30.0 {
+            if latitude >= 15.0 { "North_America" } else { "South_America" }
+        } else if latitude < -10.0 && longitude >= 110.0 {
+            "Australia"
+        } else if latitude < 35.0 && longitude < 52.0 {
+            "Africa"
+        } else {
+            "Eurasia"
+        };
+        let mut regions = vec![likely];
+        regions.extend(REGIONS.iter().filter(|&&region| region != likely));
+        return regions;
+    }
+
     fn get_latitude_orientation(latitude: f32) -> LatitudeOrientation {
         return if latitude >= 0.0 {
             LatitudeOrientation::North
@@ -132,7 +197,8 @@ impl SRTM {
             LongitudeOrientation::East => "E",
             LongitudeOrientation::West => "W"
         };
-        return format!("{}{:0>2}{}{:0>3}", north_south, (latitude.floor() as i32).to_string(), east_west, (longitude.floor() as i32).to_string());
+        // tiles are named after their south west corner, e.g. S35W059 covers -35..-34 and -59..-58
+        return format!("{}{:0>2}{}{:0>3}", north_south, (latitude.floor() as i32).abs(), east_west, (longitude.floor() as i32).abs());
     }
 }
 
@@ -143,15 +209,14 @@ impl GeoElevationFile {
         let lat_filename = latitude.floor();
         let lon_filename = longitude.floor();
         let final_square_side = square_side as i64;
-        let file = GeoElevationFile {
+        GeoElevationFile {
             file_name,
             latitude: lat_filename,
             longitude: lon_filename,
             data,
             resolution,
             square_side: final_square_side,
-        };
-        file
+        }
     }
 
     fn get_elevation(&self, latitude: f32, longitude: f32) -> Result<i16, String> {
@@ -181,8 +246,29 @@ impl GeoElevationFile {
         return Ok(elevation);
     }
 
+    /// returns the bilinear interpolation of the four samples around the coordinate, None if one of them is missing
+    fn get_bilinear_elevation(&self, latitude: f32, longitude: f32) -> Option<f32> {
+        let row = (self.latitude + 1.0 - latitude) as f64 / self.resolution;
+        let column = (longitude - self.longitude) as f64 / self.resolution;
+        let (top, left) = (row.floor() as i64, column.floor() as i64);
+        let (row_fract, column_fract) = ((row - row.floor()) as f32, (column - column.floor()) as f32);
+        let mut elevation = 0.0;
+        for &(r, c, weight) in [
+            (top, left, (1.0 - row_fract) * (1.0 - column_fract)),
+            (top, left + 1, (1.0 - row_fract) * column_fract),
+            (top + 1, left, row_fract * (1.0 - column_fract)),
+            (top + 1, left + 1, row_fract * column_fract),
+        ].iter() {
+            match self.get_elevation_from_row_and_column(r, c) {
+                Ok(ele) if ele != ELEVATION_NULL_VALUE => elevation += weight * ele as f32,
+                _ => return None,
+            }
+        }
+        return Some(elevation);
+    }
+
     fn get_interpolated_elevation(&self, latitude: f32, longitude: f32) -> f32 {
-        let mut ele_weight = self.get_elevation_weight_of_neighbors(latitude, longitude);
+        let ele_weight = self.get_elevation_weight_of_neighbors(latitude, longitude);
         // sum all weights in result
         let sum_weights: f32 = ele_weight.iter().map(|&e_w| e_w.1).sum();
         // return normalized sum
@@ -200,7 +286,7 @@ impl GeoElevationFile {
                 let weight_nearest = 1.0 / Self::distance(latitude, longitude, lat_nearest, lon_nearest);
                 ele_weight.push((ele, weight_nearest));
             }
-            Err(e) => ()
+            Err(_e) => ()
         }
         // nearest neighbors top neighbor
         match self.get_elevation_from_row_and_column(row, column - 1) {
@@ -209,7 +295,7 @@ impl GeoElevationFile {
                 let weight_west = 1.0 / Self::distance(latitude, longitude, lat_west, lon_west);
                 ele_weight.push((ele, weight_west));
             }
-            Err(e) => ()
+            Err(_e) => ()
         }
         // nearest neighbors bottom neighbor
         match self.get_elevation_from_row_and_column(row, column + 1) {
@@ -218,7 +304,7 @@ impl GeoElevationFile {
                 let weight_east = 1.0 / Self::distance(latitude, longitude, lat_east, lon_east);
                 ele_weight.push((ele, weight_east));
             }
-            Err(e) => ()
+            Err(_e) => ()
         }
         // nearest neighbors left neighbor
         match self.get_elevation_from_row_and_column(row - 1, column) {
@@ -227,7 +313,7 @@ impl GeoElevationFile {
                 let weight_north = 1.0 / Self::distance(latitude, longitude, lat_north, lon_north);
                 ele_weight.push((ele, weight_north));
             }
-            Err(e) => ()
+            Err(_e) => ()
         }
         // nearest neighbors right neighbor
         match self.get_elevation_from_row_and_column(row + 1, column) {
@@ -236,7 +322,7 @@ impl GeoElevationFile {
                 let weight_south = 1.0 / Self::distance(latitude, longitude, lat_south, lon_south);
                 ele_weight.push((ele, weight_south));
             }
-            Err(e) => ()
+            Err(_e) => ()
         }
         return ele_weight;
     }
@@ -253,18 +339,25 @@ impl GeoElevationFile {
 fn test_get_file_name() {
     let mut name = SRTM::get_file_name(53.891374, 13.083872);
     assert_eq!(name, "N53E013");
-    name = SRTM::get_file_name(46.178926, 7.639213);
+    name = SRTM::get_file_name(47.678926, 7.639213);
     // Freiburg (SW)
     assert_eq!(name, "N47E007");
+    // Madrid
+    name = SRTM::get_file_name(40.416_77, -3.703_79);
+    assert_eq!(name, "N40W004");
+    // Buenos Aires
+    name = SRTM::get_file_name(-34.603_72, -58.381_59);
+    assert_eq!(name, "S35W059");
 }
 
 #[test]
+#[allow(clippy::excessive_precision)]
 fn test_srtm() {
-    let mut srtm = SRTM::new();
+    let mut srtm = SRTM::new(Path::new(DEFAULT_SRTM_FOLDER), DEFAULT_SRTM_URL, false, Interpolation::None);
     // netherlands, sea-level
-    let sea_level_elevation = srtm.get_elevation(52.6028117, 5.2589886, false);
-    let stuttgart_elevation = srtm.get_elevation(48.785631, 9.186167, false);
-    let himalaya_elevation = srtm.get_elevation(30.3089602, 81.0986149, false);
+    let sea_level_elevation = srtm.get_elevation(52.6028117, 5.2589886).unwrap();
+    let stuttgart_elevation = srtm.get_elevation(48.785631, 9.186167).unwrap();
+    let himalaya_elevation = srtm.get_elevation(30.3089602, 81.0986149).unwrap();
     print!("{:?}", (sea_level_elevation.unwrap(), stuttgart_elevation.unwrap(), himalaya_elevation.unwrap()));
     assert!(sea_level_elevation.is_some());
     assert!(stuttgart_elevation.is_some());
@@ -273,12 +366,13 @@ fn test_srtm() {
 }
 
 #[test]
+#[allow(clippy::excessive_precision)]
 fn test_srtm_interpolate() {
-    let mut srtm = SRTM::new();
+    let mut srtm = SRTM::new(Path::new(DEFAULT_SRTM_FOLDER), DEFAULT_SRTM_URL, false, Interpolation::InverseDistance);
     // netherlands, sea-level
-    let sea_level_elevation = srtm.get_elevation(52.6028117, 5.2589886, true);
-    let stuttgart_elevation = srtm.get_elevation(48.785631, 9.186167, true);
-    let himalaya_elevation = srtm.get_elevation(30.3089602, 81.0986149, true);
+    let sea_level_elevation = srtm.get_elevation(52.6028117, 5.2589886).unwrap();
+    let stuttgart_elevation = srtm.get_elevation(48.785631, 9.186167).unwrap();
+    let himalaya_elevation = srtm.get_elevation(30.3089602, 81.0986149).unwrap();
     print!("{:?}", (sea_level_elevation.unwrap(), stuttgart_elevation.unwrap(), himalaya_elevation.unwrap()));
     assert!(sea_level_elevation.is_some());
     assert!(stuttgart_elevation.is_some());
@@ -290,5 +384,48 @@ fn test_srtm_interpolate() {
 fn test_retrieve_srtm() {
     let name = SRTM::get_file_name(47.678926, 7.639213);
     assert_eq!(name, "N47E007");
-    let result = SRTM::download_srtm_file(name.as_str()).unwrap();
-}
\ No newline at end of file
+    let srtm = SRTM::new(Path::new(DEFAULT_SRTM_FOLDER), DEFAULT_SRTM_URL, false, Interpolation::None);
+    srtm.download_srtm_file(name.as_str(), 47.678926, 7.639213).unwrap();
+}
+
+#[test]
+fn test_get_regions() {
+    assert_eq!(SRTM::get_regions(48.7856, 9.1862)[0], "Eurasia");
+    assert_eq!(SRTM::get_regions(-1.2864, 36.8172)[0], "Africa");
+    assert_eq!(SRTM::get_regions(40.7128, -74.006)[0], "North_America");
+    assert_eq!(SRTM::get_regions(-34.6037, -58.3816)[0], "South_America");
+    assert_eq!(SRTM::get_regions(-33.8688, 151.2093)[0], "Australia");
+    // every region is tried once
+    assert_eq!(SRTM::get_regions(48.7856, 9.1862).len(), REGIONS.len());
+}
+
+#[cfg(test)]
+fn write_test_tile(folder: &Path, file_name: &str) {
+    // 3x3 samples with 0.5° resolution, rows go from north to south
+    let samples: [i16; 9] = [0, 10, 20, 30, 40, 50, 60, 70, 80];
+    fs::create_dir_all(folder).unwrap();
+    let mut file = File::create(folder.join(format!("{}.hgt", file_name))).unwrap();
+    for sample in samples.iter() {
+        file.write_all(&sample.to_be_bytes()).unwrap();
+    }
+}
+
+#[test]
+fn test_srtm_offline() {
+    let folder = std::env::temp_dir().join("osm-dijkstra-pre-srtm-offline");
+    write_test_tile(&folder, "N10E010");
+    let mut srtm = SRTM::new(&folder, DEFAULT_SRTM_URL, true, Interpolation::None);
+    assert_eq!(srtm.get_elevation(10.9, 10.1), Ok(Some(0.0)));
+    // tile is not available locally and must not be downloaded
+    assert!(srtm.get_elevation(11.5, 10.5).is_err());
+}
+
+#[test]
+fn test_srtm_bilinear() {
+    let folder = std::env::temp_dir().join("osm-dijkstra-pre-srtm-bilinear");
+    write_test_tile(&folder, "N10E010");
+    let mut srtm = SRTM::new(&folder, DEFAULT_SRTM_URL, true, Interpolation::Bilinear);
+    assert_eq!(srtm.get_elevation(10.5, 10.5), Ok(Some(40.0)));
+    assert_eq!(srtm.get_elevation(10.75, 10.25), Ok(Some(20.0)));
+    assert_eq!(srtm.get_elevation(10.5, 10.75), Ok(Some(45.0)));
+}
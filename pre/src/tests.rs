@@ -1,82 +1,56 @@
 use super::*;
 
 #[test]
-fn fill_offset_test() {
-    let mut offset_test = vec![0; 8];
-    let mut ways = Vec::<Way>::new();
-    ways.push(Way {
-        source: 0,
-        target: 1,
-        speed: 2,
-        distance: 2,
-        travel_type: 1,
-    });
-    ways.push(Way {
-        source: 0,
-        target: 1,
-        speed: 2,
-        distance: 2,
-        travel_type: 1,
-    });
-    ways.push(Way {
-        source: 0,
-        target: 1,
-        speed: 2,
-        distance: 2,
-        travel_type: 1,
-    });
-    ways.push(Way {
-        source: 2,
-        target: 1,
-        speed: 2,
-        distance: 2,
-        travel_type: 1,
-    });
-    ways.push(Way {
-        source: 3,
-        target: 1,
-        speed: 2,
-        distance: 2,
-        travel_type: 1,
-    });
-    ways.push(Way {
-        source: 4,
-        target: 1,
-        speed: 2,
-        distance: 2,
-        travel_type: 1,
-    });
-    ways.push(Way {
-        source: 4,
-        target: 1,
-        speed: 2,
-        distance: 2,
-        travel_type: 1,
-    });
-    ways.push(Way {
-        source: 4,
-        target: 1,
-        speed: 2,
-        distance: 2,
-        travel_type: 1,
-    });
-    ways.push(Way {
-        source: 6,
-        target: 1,
-        speed: 2,
-        distance: 2,
-        travel_type: 1,
-    });
-    fill_offset(&ways, &mut offset_test);
+fn bounding_box_test() {
+    let bounding_box = config::parse_bounding_box("-3.9,40.3,-3.5,40.6").unwrap();
+    assert!(bounding_box.contains(40.4168, -3.7038));
+    assert!(!bounding_box.contains(48.7758, 9.1829));
+    assert!(config::parse_bounding_box("1,2,3").is_err());
+    assert!(config::parse_bounding_box("1,2,a,4").is_err());
+    assert!(config::parse_bounding_box("3,2,1,4").is_err());
+}
 
-    println!("{:?}", offset_test);
+#[test]
+fn road_class_test() {
+    assert_eq!(get_road_class("motorway", false), Some(RoadClass::CarOnly));
+    assert_eq!(get_road_class("primary", false), Some(RoadClass::CarAndBicycle));
+    assert_eq!(get_road_class("primary", true), Some(RoadClass::All));
+    assert_eq!(get_road_class("cycleway", true), Some(RoadClass::BicycleAndFoot));
+    assert_eq!(get_road_class("footway", true), Some(RoadClass::Foot));
+    assert_eq!(get_road_class("construction", false), None);
+}
+
+#[test]
+fn surface_test() {
+    assert_eq!(get_surface("asphalt"), Surface::Paved);
+    assert_eq!(get_surface("sett"), Surface::Cobblestone);
+    assert_eq!(get_surface("fine_gravel"), Surface::Compacted);
+    assert_eq!(get_surface("dirt"), Surface::Unpaved);
+    assert_eq!(get_surface("trampoline"), Surface::Unknown);
+}
+
+#[test]
+fn remove_invalid_nodes_test() {
+    let node = |latitude| Node {
+        latitude,
+        longitude: 0.0,
+        elevation: 0.0,
+    };
+    let way = |source, target| Way {
+        source,
+        target,
+        speed: 2,
+        distance: 2.0,
+        travel_type: 1,
+        surface: 0,
+    };
+    let mut nodes = vec![node(0.0), node(1.0), node(2.0), node(3.0)];
+    let mut ways = vec![way(0, 1), way(1, 2), way(2, 3), way(3, 0)];
+    remove_invalid_nodes(&mut nodes, &mut ways, &[true, false, true, true]);
 
-    assert_eq!(offset_test[0], 0);
-    assert_eq!(offset_test[1], 3);
-    assert_eq!(offset_test[2], 3);
-    assert_eq!(offset_test[3], 4);
-    assert_eq!(offset_test[4], 5);
-    assert_eq!(offset_test[5], 8);
-    assert_eq!(offset_test[6], 8);
-    assert_eq!(offset_test[7], 9);
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(nodes[1].latitude, 2.0);
+    assert_eq!(ways.len(), 2);
+    assert_eq!((ways[0].source, ways[0].target), (1, 2));
+    assert_eq!((ways[1].source, ways[1].target), (2, 0));
 }
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use clap::{App, Arg};
+
+#[derive(Clone)]
+pub struct Config {
+    pub graph_file: PathBuf,
+    pub bind_address: String,
+    pub port: u16,
+    pub static_folder: PathBuf,
+    pub json_limit: usize,
+    /// maximum amount of entries (sources times targets) of a distance matrix
+    pub matrix_limit: usize,
+    /// toml file with the routing profiles requests can select
+    pub profiles: Option<PathBuf>,
+}
+
+impl Config {
+    /// parses the command line arguments, every option can also be set through an environment variable
+    pub fn from_args() -> Config {
+        let matches = App::new("osm-dijkstra-web")
+            .version(env!("CARGO_PKG_VERSION"))
+            .about("Serves the bike route planner for a *.fmi graph created by pre")
+            .arg(Arg::with_name("graph")
+                .help("fmi file created by pre")
+                .env("OSM_DIJKSTRA_GRAPH")
+                .required(true)
+                .index(1))
+            .arg(Arg::with_name("bind")
+                .short("b")
+                .long("bind")
+                .value_name("ADDRESS")
+                .env("OSM_DIJKSTRA_BIND")
+                .default_value("localhost")
+                .help("address the webserver listens on, use 0.0.0.0 inside of containers"))
+            .arg(Arg::with_name("port")
+                .short("p")
+                .long("port")
+                .value_name("PORT")
+                .env("OSM_DIJKSTRA_PORT")
+                .default_value("8080")
+                .validator(|v| v.parse::<u16>().map(|_| ()).map_err(|_e| format!("'{}' is not a valid port", v)))
+                .help("port the webserver listens on"))
+            .arg(Arg::with_name("static-dir")
+                .long("static-dir")
+                .value_name("DIR")
+                .env("OSM_DIJKSTRA_STATIC_DIR")
+                .default_value("./static")
+                .help("directory containing the leaflet frontend"))
+            .arg(Arg::with_name("json-limit")
+                .long("json-limit")
+                .value_name("BYTES")
+                .env("OSM_DIJKSTRA_JSON_LIMIT")
+                .default_value("65536")
+                .validator(|v| v.parse::<usize>().map(|_| ()).map_err(|_e| format!("'{}' is not a valid size", v)))
+                .help("maximum size of a json request body, enough for the largest matrix and via point requests by default"))
+            .arg(Arg::with_name("matrix-limit")
+                .long("matrix-limit")
+                .value_name("ENTRIES")
+                .env("OSM_DIJKSTRA_MATRIX_LIMIT")
+                .default_value("2500")
+                .validator(|v| v.parse::<usize>().map(|_| ()).map_err(|_e| format!("'{}' is not a valid size", v)))
+                .help("maximum amount of sources times targets of a distance matrix request"))
+            .arg(Arg::with_name("profiles")
+                .long("profiles")
+                .value_name("FILE")
+                .env("OSM_DIJKSTRA_PROFILES")
+                .help("toml file with routing profiles, e.g. profiles.toml"))
+            .get_matches();
+
+        return Config {
+            graph_file: PathBuf::from(matches.value_of_os("graph").unwrap()),
+            bind_address: matches.value_of("bind").unwrap().to_string(),
+            port: matches.value_of("port").unwrap().parse().unwrap(),
+            static_folder: PathBuf::from(matches.value_of_os("static-dir").unwrap()),
+            json_limit: matches.value_of("json-limit").unwrap().parse().unwrap(),
+            matrix_limit: matches.value_of("matrix-limit").unwrap().parse().unwrap(),
+            profiles: matches.value_of_os("profiles").map(PathBuf::from),
+        };
+    }
+}
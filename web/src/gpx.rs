@@ -0,0 +1,56 @@
+use std::fmt::{Result, Write};
+
+use fmi::Node;
+
+/// route written as track of a GPX file
+pub struct Track<'a> {
+    pub name: String,
+    /// length, travel time and ascent of the route for humans
+    pub description: String,
+    pub path: &'a [Node],
+}
+
+/// writes the tracks as GPX 1.1 file, every node of a path becomes a track point with its elevation
+pub fn write_gpx(name: &str, description: &str, tracks: &[Track]) -> String {
+    let mut gpx = String::new();
+    write_document(&mut gpx, name, description, tracks).expect("writing to a string can not fail");
+    return gpx;
+}
+
+fn write_document(gpx: &mut String, name: &str, description: &str, tracks: &[Track]) -> Result {
+    writeln!(gpx, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(gpx, r#"<gpx version="1.1" creator="osm-dijkstra-web" xmlns="http://www.topografix.com/GPX/1/1">"#)?;
+    writeln!(gpx, "  <metadata>")?;
+    writeln!(gpx, "    <name>{}</name>", escape(name))?;
+    writeln!(gpx, "    <desc>{}</desc>", escape(description))?;
+    writeln!(gpx, "  </metadata>")?;
+    for track in tracks {
+        writeln!(gpx, "  <trk>")?;
+        writeln!(gpx, "    <name>{}</name>", escape(&track.name))?;
+        writeln!(gpx, "    <desc>{}</desc>", escape(&track.description))?;
+        writeln!(gpx, "    <trkseg>")?;
+        for node in track.path {
+            writeln!(gpx, r#"      <trkpt lat="{}" lon="{}"><ele>{}</ele></trkpt>"#, node.latitude, node.longitude, node.elevation)?;
+        }
+        writeln!(gpx, "    </trkseg>")?;
+        writeln!(gpx, "  </trk>")?;
+    }
+    writeln!(gpx, "</gpx>")?;
+    return Ok(());
+}
+
+/// escapes the characters with a meaning in xml
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(character),
+        }
+    }
+    return escaped;
+}
@@ -1,21 +1,119 @@
 // based on https://rosettacode.org/wiki/Dijkstra%27s_algorithm#Rust
+use std::cell::RefCell;
 use std::cmp::{Ordering};
-use std::collections::{BinaryHeap, HashMap};
-use std::usize;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
 
-use Node;
-use Way;
+use fmi::{calc_distance, ContractionHierarchy, get_gradient_percent, get_grid_cell, get_walking_speed, get_way_weight, Grid, is_way_allowed, MapData, MAX_BICYCLE_SPEED, Node, Rider, Storage, TravelType, Way};
+use serde::{Deserialize, Serialize};
 
-// First three digits of coordinates are used for the grid hashing
-const GRID_MULTIPLICATOR: usize = 100;
-const MAX_F64: f64 = std::f64::MAX;
+use profile::Profile;
+use workspace::{Labels, SearchWorkspace};
+
+const MAX_F64: f64 = f64::MAX;
+/// keeps the A* estimate below the real costs despite f32 rounding of the stored distances and elevations
+const ESTIMATE_SLACK: f64 = 0.999;
+/// descent weights LARAC tries at most if rise and descent are limited
+const DESCENT_WEIGHT_ROUNDS: usize = 8;
+/// the amount of pareto optimal paths can grow exponentially, the pareto and the exact search are aborted after this many labels
+pub const LABEL_LIMIT: usize = 2_000_000;
+/// points are only snapped to nodes at most this many grid cells (about 1 km each) away
+const MAX_SNAP_RINGS: i32 = 20;
+/// costs of avoided ways are multiplied with this, so they are only used if a detour is much longer
+const AVOIDED_WAY_PENALTY: f64 = 4.0;
+
+thread_local! {
+    /// every worker thread keeps its search arrays, they are reused by all searches of all requests it answers
+    static WORKSPACE: RefCell<SearchWorkspace<State>> = RefCell::new(SearchWorkspace::new());
+}
+
+/// memory used by the search workspace of the current thread in bytes
+pub fn workspace_byte_size() -> usize {
+    return WORKSPACE.with(|workspace| workspace.borrow().byte_size());
+}
 
 #[derive(Clone)]
 pub struct Graph {
-    nodes: Vec<Node>,
-    ways: Vec<Way>,
-    offset: Vec<usize>,
-    grid: HashMap<(usize, usize), Vec<usize>>,
+    nodes: Storage<Node>,
+    ways: Storage<Way>,
+    offset: Storage<u32>,
+    reverse_way_ids: Storage<u32>,
+    reverse_offset: Storage<u32>,
+    grid: Grid,
+    hierarchies: Vec<ContractionHierarchy>,
+    /// highest speed of all ways in km/h, used for the A* estimate in time mode
+    max_speed: u32,
+}
+
+/// which ways a search may use and how it counts elevation
+#[derive(Copy, Clone, Debug)]
+pub struct Criteria<'a> {
+    pub travel_type: TravelType,
+    /// speeds and access of the profile instead of the defaults of the travel type
+    pub profile: Option<&'a Profile>,
+    /// power and mass of bicycle riders for their speed on slopes
+    pub rider: Rider,
+    /// ways that are steeper uphill or downhill are not used, in percent
+    pub max_gradient_percent: Option<f64>,
+    /// ways that rise more are not used, in m
+    pub max_segment_climb: Option<f64>,
+    /// the elevation costs of a way are its weighted rise plus its weighted descent, LARAC uses them to limit the descent
+    pub rise_weight: f64,
+    pub descent_weight: f64,
+    /// ways between these nodes, sorted by id, cost AVOIDED_WAY_PENALTY times as much in both directions
+    pub avoided_ways: Option<&'a HashSet<(usize, usize)>>,
+}
+
+impl<'a> Criteria<'a> {
+    /// every way of the travel type can be used, the elevation costs are the rise
+    pub fn new(travel_type: TravelType) -> Criteria<'a> {
+        return Criteria {
+            travel_type,
+            profile: None,
+            rider: Rider::DEFAULT,
+            max_gradient_percent: None,
+            max_segment_climb: None,
+            rise_weight: 1.0,
+            descent_weight: 0.0,
+            avoided_ways: None,
+        };
+    }
+
+    /// the ways of the profile can be used with its speeds, the elevation costs are the rise
+    pub fn with_profile(profile: &'a Profile) -> Criteria<'a> {
+        return Criteria {
+            profile: Some(profile),
+            ..Criteria::new(profile.travel_type)
+        };
+    }
+
+    /// the contraction hierarchies of pre can only be used if ways are filtered and weighted by travel type alone
+    fn filters_by_travel_type_only(&self) -> bool {
+        return self.profile.is_none()
+            && self.rider == Rider::DEFAULT
+            && self.max_gradient_percent.is_none()
+            && self.max_segment_climb.is_none()
+            && self.avoided_ways.is_none();
+    }
+
+    /// checks if the travel type and the profile may use the way, independent of its steepness
+    pub fn may_use(&self, way: &Way) -> bool {
+        if !is_way_allowed(way, self.travel_type) {
+            return false;
+        }
+        return match self.profile {
+            Some(profile) => profile.may_use(way),
+            None => true,
+        };
+    }
+
+    fn with_elevation_weights(&self, rise_weight: f64, descent_weight: f64) -> Criteria<'a> {
+        return Criteria {
+            rise_weight,
+            descent_weight,
+            ..*self
+        };
+    }
 }
 
 #[derive(Clone)]
@@ -23,6 +121,7 @@ pub struct DijkstraResult {
     pub path: Vec<usize>,
     pub distance: f64,
     pub ele_rise: f64,
+    pub descent: f64,
     pub multiplier: Option<f64>,
 }
 
@@ -32,6 +131,104 @@ impl PartialEq for DijkstraResult {
     }
 }
 
+/// elevation of a node of a route and its distance from the start along the route in km
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ProfilePoint {
+    pub distance: f64,
+    pub elevation: f64,
+}
+
+/// elevation statistics of a route, elevations in m and gradients in percent
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RouteStatistics {
+    pub ascent: f64,
+    pub descent: f64,
+    pub min_elevation: f64,
+    pub max_elevation: f64,
+    /// steepest way uphill or downhill
+    pub max_gradient_percent: f64,
+    /// ascent plus descent per length, the absolute gradients of the ways weighted by their length
+    pub average_gradient_percent: f64,
+    /// highest rise of a single way
+    pub max_segment_climb: f64,
+    /// one point per node of the route
+    pub profile: Vec<ProfilePoint>,
+}
+
+/// paths found for a request
+pub struct Routes {
+    pub paths: Vec<DijkstraResult>,
+    /// no path within the elevation limit is shorter (or faster), only known if there is a limit
+    pub lower_bound: Option<f64>,
+    /// the last path is proven to be the shortest (or fastest) within the limits
+    pub exact: bool,
+}
+
+/// reasons why a request can not be answered, serialized with a machine readable code and the details of the variant
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum RoutingError {
+    /// parameters of the request that can not be used
+    InvalidRequest { reason: String },
+    /// the request contains more points or asks for more results than allowed, what names them
+    RequestTooLarge { what: &'static str, amount: usize, limit: usize },
+    /// the json body of the request is larger than the limit in bytes
+    BodyTooLarge { limit: usize },
+    /// there is no node of the travel type near the point
+    NotSnapped { latitude: f32, longitude: f32 },
+    /// the waypoints are not connected by ways of the travel type
+    NoPath,
+    /// every path exceeds a limit, the lowest elevation rise and descent of any path are each reachable on their own
+    ElevationInfeasible { min_ele_rise: f64, min_descent: f64 },
+    /// the heuristic did not find a path within the limits, although there may be one
+    NoPathWithinLimits,
+    /// an exact search created more labels than allowed
+    LabelLimitExceeded { limit: usize },
+}
+
+impl fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            RoutingError::InvalidRequest { reason } => write!(f, "{}", reason),
+            RoutingError::RequestTooLarge { what, amount, limit } => write!(f, "{} {} given, at most {} are allowed", amount, what, limit),
+            RoutingError::BodyTooLarge { limit } => write!(f, "The request body is larger than {} bytes", limit),
+            RoutingError::NotSnapped { latitude, longitude } => write!(f, "There is no way of the travel type near {},{}", latitude, longitude),
+            RoutingError::NoPath => write!(f, "There is no path between the waypoints"),
+            RoutingError::ElevationInfeasible { min_ele_rise, min_descent } => {
+                write!(f, "There is no path within the limits, the minimal elevation rise is {} m and the minimal descent {} m", min_ele_rise, min_descent)
+            }
+            RoutingError::NoPathWithinLimits => write!(f, "Did not find a path within the elevation and descent limit"),
+            RoutingError::LabelLimitExceeded { limit } => write!(f, "The search exceeded the limit of {} labels", limit),
+        };
+    }
+}
+
+/// node found by a one to all search with the cheapest path to it
+#[derive(Copy, Clone, Debug)]
+pub struct Reachable {
+    pub node: usize,
+    /// distance in km or travel time in h
+    pub cost: f64,
+    /// elevation rise of the path in m
+    pub ele_rise: f64,
+}
+
+/// result of LARAC for an elevation limit
+struct Larac<'a> {
+    /// paths within the elevation limit, the last one is the optimal one found
+    paths: Vec<DijkstraResult>,
+    /// shortest path within the limit of the elevation costs of the last run, it may exceed one of the original limits
+    best: DijkstraResult,
+    /// elevation weights of the last run
+    criteria: Criteria<'a>,
+    /// the last multiplier of the elevation costs
+    multiplier: f64,
+    /// limit of the elevation costs of the last run
+    limit: f64,
+    /// highest lagrangian lower bound of the iterations
+    lower_bound: f64,
+}
+
 pub enum Dijkstra {
     Elevation,
     Multiplier,
@@ -41,17 +238,16 @@ pub enum Dijkstra {
 struct State {
     node: usize,
     cost: f64,
+    /// cost plus the estimated remaining cost to the target, the heap is ordered by it
+    priority: f64,
     distance: f64,
     ele_rise: f64,
+    descent: f64,
 }
 
 impl PartialEq for State {
     fn eq(&self, other: &Self) -> bool {
-        return self.node == other.node && self.cost == other.cost;
-    }
-
-    fn ne(&self, other: &Self) -> bool {
-        return !self.eq(other);
+        return self.node == other.node && self.priority == other.priority;
     }
 }
 
@@ -60,7 +256,7 @@ impl Eq for State {}
 // Manually implement Ord so we get a min-heap instead of a max-heap
 impl Ord for State {
     fn cmp(&self, other: &Self) -> Ordering {
-        let cmp = other.cost - self.cost;
+        let cmp = other.priority - self.priority;
         if cmp == 0.0 { return Ordering::Equal; };
         if cmp > 0.0 { return Ordering::Greater; }
         return Ordering::Less;
@@ -73,49 +269,177 @@ impl PartialOrd for State {
     }
 }
 
+/// partial path of the pareto search
+#[derive(Copy, Clone)]
+struct ParetoLabel {
+    node: usize,
+    /// index of the leg the path is in, the amount of legs once it reached the end
+    leg: usize,
+    distance: f64,
+    ele_rise: f64,
+    descent: f64,
+    previous: Option<usize>,
+    /// set when a label of the same node that is at least as good in all costs is found
+    dominated: bool,
+}
+
+#[derive(Copy, Clone)]
+struct ParetoState {
+    label: usize,
+    /// lower bound of the costs of a complete path through the label, the heap is ordered by it
+    priority: f64,
+    /// elevation rise plus the estimated remaining elevation rise, breaks ties of the priority
+    ele_priority: f64,
+}
+
+impl PartialEq for ParetoState {
+    fn eq(&self, other: &Self) -> bool {
+        return self.priority == other.priority && self.ele_priority == other.ele_priority;
+    }
+}
+
+impl Eq for ParetoState {}
+
+// min-heap ordered lexicographically by (priority, ele_priority)
+impl Ord for ParetoState {
+    fn cmp(&self, other: &Self) -> Ordering {
+        return other.priority.partial_cmp(&self.priority)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.ele_priority.partial_cmp(&self.ele_priority).unwrap_or(Ordering::Equal));
+    }
+}
+
+impl PartialOrd for ParetoState {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// checks if one of the labels is at least as good as the given costs in distance, elevation rise and descent if given
+fn is_dominated(labels: &[ParetoLabel], bag: Option<&Vec<usize>>, distance: f64, ele_rise: f64, descent: Option<f64>) -> bool {
+    return match bag {
+        Some(bag) => bag.iter().any(|&id| {
+            labels[id].distance <= distance && labels[id].ele_rise <= ele_rise && descent.is_none_or(|descent| labels[id].descent <= descent)
+        }),
+        None => false,
+    };
+}
+
+/// adds a label to the labels of its node and marks the labels it dominates
+fn add_label(labels: &mut Vec<ParetoLabel>, bag: &mut Vec<usize>, label: ParetoLabel, compare_descent: bool) {
+    bag.retain(|&other| {
+        let other = &mut labels[other];
+        other.dominated = label.distance <= other.distance
+            && label.ele_rise <= other.ele_rise
+            && (!compare_descent || label.descent <= other.descent);
+        return !other.dominated;
+    });
+    bag.push(labels.len());
+    labels.push(label);
+}
+
+/// a route needs a start and an end, there may be via points between them
+fn check_waypoints(waypoints: &[usize]) -> Result<(), RoutingError> {
+    if waypoints.len() < 2 {
+        return Err(RoutingError::InvalidRequest {
+            reason: "A route needs at least a start and an end".to_string(),
+        });
+    }
+    return Ok(());
+}
+
+/// returns the leg a path continues with after reaching the node
+///
+/// A leg ends with the first visit of its waypoint, a path to the end of the last leg is in leg waypoints.len() - 1.
+fn get_next_leg(waypoints: &[usize], leg: usize, node: usize) -> usize {
+    let mut leg = leg;
+    while leg + 1 < waypoints.len() && waypoints[leg + 1] == node {
+        leg += 1;
+    }
+    return leg;
+}
+
+/// returns the sums of the values of every leg and all legs after it, followed by 0 for the end
+fn get_suffix_sums<I: DoubleEndedIterator<Item = f64>>(values: I) -> Vec<f64> {
+    let mut sums = vec![0.0];
+    for value in values.rev() {
+        sums.push(sums[sums.len() - 1] + value);
+    }
+    sums.reverse();
+    return sums;
+}
+
+/// returns the index of the last node of every leg in the path of a route through the waypoints
+pub fn get_leg_ends(path: &[usize], waypoints: &[usize]) -> Vec<usize> {
+    let mut ends = Vec::with_capacity(waypoints.len().saturating_sub(1));
+    let mut index = 0;
+    for &waypoint in waypoints.iter().skip(1) {
+        // legs end at the first visit of their waypoint, like in the searches
+        while index < path.len() - 1 && path[index] != waypoint {
+            index += 1;
+        }
+        ends.push(index);
+    }
+    return ends;
+}
+
+/// follows the previous labels back to the start
+fn get_label_path(labels: &[ParetoLabel], id: usize) -> DijkstraResult {
+    let mut path = vec![labels[id].node];
+    let mut current = &labels[id];
+    while let Some(previous) = current.previous {
+        current = &labels[previous];
+        path.push(current.node);
+    }
+    path.reverse();
+    return DijkstraResult {
+        path,
+        distance: labels[id].distance,
+        ele_rise: labels[id].ele_rise,
+        descent: labels[id].descent,
+        multiplier: None,
+    };
+}
+
+/// follows the predecessors of a Dijkstra search back from end to the start
+fn get_predecessor_path(labels: &Labels, end: usize) -> Vec<usize> {
+    let mut path = vec![end];
+    let mut current = labels.get(end);
+    while let Some(previous) = current.1 {
+        path.push(previous);
+        current = labels.get(previous);
+    }
+    path.reverse();
+    return path;
+}
+
 impl Graph {
-    pub fn new(
-        nodes: Vec<Node>,
-        ways: Vec<Way>,
-        offset: Vec<usize>,
-        grid: HashMap<(usize, usize), Vec<usize>>,
-    ) -> Self {
+    /// takes over the arrays of the map data, memory mapped arrays stay in place
+    pub fn new(map_data: MapData) -> Self {
+        let max_speed = map_data.ways.iter().map(|way| way.speed).max().unwrap_or(0);
         Graph {
-            nodes,
-            ways,
-            offset,
-            grid,
-        }
-    }
-
-    /// returns closest point of given long & lat
-    pub fn get_point_id(&self, lat: f32, long: f32, travel_type: usize) -> usize {
-        let mut min_distance: f32 = std::f32::MAX;
-        let mut min_distance_id: usize = 0;
-        let allowed_types = self.get_allowed_types(travel_type);
-        let adjacent_nodes = self.get_adjacent_node_ids(lat, long, &allowed_types);
-        for node_id in adjacent_nodes {
-            match self.nodes.get(node_id) {
-                Some(node) => {
-                    let distance = calc_distance(lat, long, node.latitude, node.longitude);
-                    if distance < min_distance {
-                        min_distance = distance;
-                        min_distance_id = node_id;
-                    }
-                }
-                None => continue
-            }
+            nodes: map_data.nodes,
+            ways: map_data.ways,
+            offset: map_data.offset,
+            reverse_way_ids: map_data.reverse_way_ids,
+            reverse_offset: map_data.reverse_offset,
+            grid: map_data.grid,
+            hierarchies: map_data.hierarchies,
+            max_speed,
         }
-        return min_distance_id;
     }
 
-    fn get_allowed_types(&self, road_type: usize) -> Vec<usize> {
-        return match road_type {
-            0 => vec![0, 1, 5],
-            1 => vec![1, 2, 3, 5],
-            2 => vec![4, 5],
-            _ => vec![5]
-        };
+    /// returns closest point of given long & lat with a way the criteria may use, None if there is none within MAX_SNAP_RINGS grid cells
+    pub fn get_point_id(&self, lat: f32, long: f32, criteria: &Criteria) -> Option<usize> {
+        let adjacent_nodes = self.get_adjacent_node_ids(lat, long, criteria);
+        return adjacent_nodes
+            .into_iter()
+            .map(|node_id| {
+                let node = &self.nodes[node_id];
+                (node_id, calc_distance(lat, long, node.latitude, node.longitude))
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .map(|(node_id, _)| node_id);
     }
 
     /// converts node ids to node-coordinates
@@ -128,8 +452,8 @@ impl Graph {
     }
 
     fn get_edge_elevation_rise(&self, way: Way) -> f64 {
-        let source_ele = self.get_node(way.source).elevation;
-        let target_ele = self.get_node(way.target).elevation;
+        let source_ele = self.get_node(way.source as usize).elevation;
+        let target_ele = self.get_node(way.target as usize).elevation;
         let delta = (target_ele - source_ele) as f64;
         return if delta > 0.0 {
             delta
@@ -138,22 +462,56 @@ impl Graph {
         };
     }
 
-    /// returns the edge weight from source to target
-    fn get_edge_distance(&self, way: Way, travel_type: usize, use_distance: bool) -> f64 {
-        return if use_distance {
-            way.distance as f64
-        } else {
-            if way.speed == 0 {
-                return way.distance as f64;
-            }
-            let speed = match travel_type {
-                0 => way.speed,
-                1 if way.speed <= 20 => way.speed,
-                1 if way.speed >= 20 => 20,
-                2 => 7,
-                _ => unreachable!(),
-            };
-            way.distance as f64 / speed as f64
+    /// elevation rise from one node to another, 0 if the second node is lower
+    fn get_elevation_difference(&self, from: usize, to: usize) -> f64 {
+        return ((self.nodes[to].elevation - self.nodes[from].elevation) as f64).max(0.0);
+    }
+
+    fn get_edge_descent(&self, way: Way) -> f64 {
+        let source_ele = self.get_node(way.source as usize).elevation;
+        let target_ele = self.get_node(way.target as usize).elevation;
+        return ((source_ele - target_ele) as f64).max(0.0);
+    }
+
+    /// elevation difference per length in percent, negative downhill. Ways without length are flat
+    fn get_edge_gradient(&self, way: Way) -> f64 {
+        return get_gradient_percent(&way, &self.nodes);
+    }
+
+    /// the elevation costs of a way, its weighted rise plus its weighted descent
+    fn get_edge_elevation(&self, way: Way, criteria: &Criteria) -> f64 {
+        let mut elevation = criteria.rise_weight * self.get_edge_elevation_rise(way);
+        if criteria.descent_weight > 0.0 {
+            elevation += criteria.descent_weight * self.get_edge_descent(way);
+        }
+        return elevation;
+    }
+
+    /// returns the edge weight from source to target, travel times depend on the gradient for bicycles and walkers
+    fn get_edge_distance(&self, way: Way, criteria: &Criteria, use_distance: bool) -> f64 {
+        let cost = match criteria.profile {
+            Some(profile) if !use_distance => way.distance as f64 / profile.get_speed(&way, self.get_edge_gradient(way)),
+            _ => get_way_weight(&way, criteria.travel_type, use_distance, self.get_edge_gradient(way), &criteria.rider),
+        };
+        let (source, target) = (way.source as usize, way.target as usize);
+        return match criteria.avoided_ways {
+            Some(avoided_ways) if avoided_ways.contains(&(source.min(target), source.max(target))) => cost * AVOIDED_WAY_PENALTY,
+            _ => cost,
+        };
+    }
+
+    /// highest speed a way can be used with by the travel type on any gradient, ways without speed count as 1 km/h for cars only
+    fn get_max_speed(&self, criteria: &Criteria) -> f64 {
+        if let Some(profile) = criteria.profile {
+            return profile.get_max_speed();
+        }
+        let max_speed = self.max_speed.max(1) as f64;
+        return match criteria.travel_type {
+            TravelType::Car => max_speed,
+            // ways without speed do not cap bicycles
+            TravelType::Bicycle => MAX_BICYCLE_SPEED,
+            // walkers are fastest on a descent of 5 %
+            TravelType::Foot => get_walking_speed(-5.0),
         };
     }
 
@@ -161,46 +519,148 @@ impl Graph {
         return distance + (multiplier * elevation);
     }
 
-    fn is_valid_node_for_travel_type(&self, node_id: usize, allowed_types: &Vec<usize>) -> bool {
-        let incl_start = self.offset[node_id];
-        let excl_end = self.offset[node_id + 1];
+    /// returns the cost the search minimizes for an edge with the given distance and elevation rise
+    fn get_edge_cost(&self, min_of: &Dijkstra, distance: f64, ele_rise: f64, multiplier: Option<f64>) -> f64 {
+        return match min_of {
+            Dijkstra::Elevation => ele_rise,
+            Dijkstra::Multiplier => match multiplier {
+                Some(multiplier) => self.get_weight_with_multiplier(distance, ele_rise, multiplier),
+                None => panic!("Dijkstra was called in multiplier mode but no multiplier was provided")
+            }
+        };
+    }
+
+    /// returns the cheapest usable way from source to target
+    fn get_way(&self, source: usize, target: usize, criteria: &Criteria, use_distance: bool) -> Option<Way> {
+        let ways = &self.ways[self.offset[source] as usize..self.offset[source + 1] as usize];
+        return ways
+            .iter()
+            .filter(|way| way.target as usize == target && self.is_way_allowed(way, criteria))
+            .min_by(|a, b| self.get_edge_distance(**a, criteria, use_distance).partial_cmp(&self.get_edge_distance(**b, criteria, use_distance)).unwrap())
+            .copied();
+    }
+
+    /// returns the length in km and the travel time in h of a path found with the criteria
+    pub fn get_path_length_and_duration(&self, path: &[usize], criteria: &Criteria, use_distance: bool) -> (f64, f64) {
+        let mut length = 0.0;
+        let mut duration = 0.0;
+        for pair in path.windows(2) {
+            if let Some(way) = self.get_way(pair[0], pair[1], criteria, use_distance) {
+                length += way.distance as f64;
+                duration += self.get_edge_distance(way, criteria, false);
+            }
+        }
+        return (length, duration);
+    }
+
+    /// computes the elevation statistics of a path found with the criteria
+    pub fn get_route_statistics(&self, path: &[usize], criteria: &Criteria, use_distance: bool) -> RouteStatistics {
+        let mut statistics = RouteStatistics {
+            ascent: 0.0,
+            descent: 0.0,
+            min_elevation: 0.0,
+            max_elevation: 0.0,
+            max_gradient_percent: 0.0,
+            average_gradient_percent: 0.0,
+            max_segment_climb: 0.0,
+            profile: Vec::with_capacity(path.len()),
+        };
+        let first = match path.first() {
+            Some(&first) => self.nodes[first].elevation as f64,
+            None => return statistics,
+        };
+        statistics.min_elevation = first;
+        statistics.max_elevation = first;
+        statistics.profile.push(ProfilePoint {
+            distance: 0.0,
+            elevation: first,
+        });
+        let mut distance = 0.0;
+        for pair in path.windows(2) {
+            let (source, target) = (self.nodes[pair[0]], self.nodes[pair[1]]);
+            match self.get_way(pair[0], pair[1], criteria, use_distance) {
+                Some(way) => {
+                    distance += way.distance as f64;
+                    statistics.ascent += self.get_edge_elevation_rise(way);
+                    statistics.descent += self.get_edge_descent(way);
+                    statistics.max_gradient_percent = statistics.max_gradient_percent.max(self.get_edge_gradient(way).abs());
+                    statistics.max_segment_climb = statistics.max_segment_climb.max(self.get_edge_elevation_rise(way));
+                }
+                None => distance += calc_distance(source.latitude, source.longitude, target.latitude, target.longitude) as f64,
+            }
+            let elevation = target.elevation as f64;
+            statistics.min_elevation = statistics.min_elevation.min(elevation);
+            statistics.max_elevation = statistics.max_elevation.max(elevation);
+            statistics.profile.push(ProfilePoint {
+                distance,
+                elevation,
+            });
+        }
+        if distance > 0.0 {
+            // distances are in km, elevations in m
+            statistics.average_gradient_percent = (statistics.ascent + statistics.descent) / (distance * 1000.0) * 100.0;
+        }
+        return statistics;
+    }
+
+    /// checks if the way can be used with the travel type and is not too steep
+    fn is_way_allowed(&self, way: &Way, criteria: &Criteria) -> bool {
+        if !criteria.may_use(way) {
+            return false;
+        }
+        if let Some(max_gradient_percent) = criteria.max_gradient_percent {
+            if self.get_edge_gradient(*way).abs() > max_gradient_percent {
+                return false;
+            }
+        }
+        if let Some(max_segment_climb) = criteria.max_segment_climb {
+            if self.get_edge_elevation_rise(*way) > max_segment_climb {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// checks if the node has an outgoing way of the travel type and profile, so snapped points are where searches can start
+    fn is_valid_node_for_travel_type(&self, node_id: usize, criteria: &Criteria) -> bool {
+        let incl_start = self.offset[node_id] as usize;
+        let excl_end = self.offset[node_id + 1] as usize;
         for i in incl_start..excl_end {
             let edge = &self.ways[i];
-            if allowed_types.contains(&edge.travel_type) {
+            if criteria.may_use(edge) {
                 return true;
             }
         }
         return false;
     }
 
-    fn add_valid_node_ids_from_cell(&self, node_ids: &mut Vec<usize>, cell: &(usize, usize), allowed_types: &Vec<usize>) {
+    fn add_valid_node_ids_from_cell(&self, node_ids: &mut Vec<usize>, cell: &(i32, i32), criteria: &Criteria) {
         match self.grid.get(cell) {
-            Some(adjacent_node_ids) => node_ids.extend(adjacent_node_ids.iter().filter(|&&x| self.is_valid_node_for_travel_type(x, allowed_types)).collect::<Vec<&usize>>()),
+            Some(adjacent_node_ids) => node_ids.extend(adjacent_node_ids.iter().map(|&x| x as usize).filter(|&x| self.is_valid_node_for_travel_type(x, criteria))),
             None => return
         }
     }
 
 
     /// returns node_ids in adjacent grid cells
-    /// goes from most inner cell to cells with distance 1 to n until a node is found
-    fn get_adjacent_node_ids(&self, lat: f32, lng: f32, allowed_types: &Vec<usize>) -> Vec<usize> {
-        let lat_grid = (lat * GRID_MULTIPLICATOR as f32) as i32;
-        let lng_grid = (lng * GRID_MULTIPLICATOR as f32) as i32;
+    /// goes from most inner cell to cells with distance 1 to MAX_SNAP_RINGS until a node is found
+    fn get_adjacent_node_ids(&self, lat: f32, lng: f32, criteria: &Criteria) -> Vec<usize> {
+        let (lat_grid, lng_grid) = get_grid_cell(lat, lng);
         let mut node_ids = Vec::<usize>::new();
-        self.add_valid_node_ids_from_cell(&mut node_ids, &(lat_grid as usize, lng_grid as usize), allowed_types);
+        self.add_valid_node_ids_from_cell(&mut node_ids, &(lat_grid, lng_grid), criteria);
         let mut in_dist: i32 = 1;
         loop {
             for i in -in_dist..in_dist {
                 // top row left to right (increasing x, fix y)
-                self.add_valid_node_ids_from_cell(&mut node_ids, &((lat_grid + i) as usize, (lng_grid + in_dist) as usize), allowed_types);
+                self.add_valid_node_ids_from_cell(&mut node_ids, &(lat_grid + i, lng_grid + in_dist), criteria);
                 // right column top to bottom (fix x, decreasing y)
-                self.add_valid_node_ids_from_cell(&mut node_ids, &((lat_grid + in_dist) as usize, (lng_grid - i) as usize), allowed_types);
+                self.add_valid_node_ids_from_cell(&mut node_ids, &(lat_grid + in_dist, lng_grid - i), criteria);
                 // bottom row right to left (decreasing x, fix y)
-                self.add_valid_node_ids_from_cell(&mut node_ids, &((lat_grid - i) as usize, (lng_grid - in_dist) as usize), allowed_types);
+                self.add_valid_node_ids_from_cell(&mut node_ids, &(lat_grid - i, lng_grid - in_dist), criteria);
                 // left column bottom to top (fix x, increasing y)
-                self.add_valid_node_ids_from_cell(&mut node_ids, &((lat_grid - in_dist) as usize, (lng_grid + i) as usize), allowed_types);
+                self.add_valid_node_ids_from_cell(&mut node_ids, &(lat_grid - in_dist, lng_grid + i), criteria);
             }
-            if node_ids.len() > 0 {
+            if !node_ids.is_empty() || in_dist == MAX_SNAP_RINGS {
                 return node_ids;
             } else {
                 // search in next level cells
@@ -210,36 +670,171 @@ impl Graph {
     }
 
     /// executes the LARAC (Lagrange Relaxation based Aggregated Cost) algorithm and returns the shortest path with max_elevation as well as more recommendations below the max_elevation level
-    pub fn find_optimal_path(&self, start: usize, end: usize, travel_type: usize, use_distance: bool, max_elevation: f64, all_paths: bool) -> Result<Vec<DijkstraResult>, String> {
+    /// max_descent limits the total descent in the same way, without any limit only the shortest path is returned
+    /// The route leads from the first to the last waypoint through the others in order, the limits are shared by all legs.
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_optimal_path(&self, waypoints: &[usize], criteria: Criteria, use_distance: bool, max_elevation: Option<f64>, max_descent: Option<f64>, all_paths: bool) -> Result<Routes, RoutingError> {
+        check_waypoints(waypoints)?;
+        if max_elevation.is_none() && max_descent.is_none() {
+            return self.find_shortest_route(waypoints, criteria, use_distance);
+        }
+        let larac = self.larac(waypoints, criteria, use_distance, max_elevation, max_descent, all_paths)?;
+        let exact = larac.paths.last().is_some_and(|best| best.distance <= larac.lower_bound);
+        return Ok(Routes {
+            paths: larac.paths,
+            lower_bound: Some(larac.lower_bound),
+            exact,
+        });
+    }
+
+    fn find_shortest_route(&self, waypoints: &[usize], criteria: Criteria, use_distance: bool) -> Result<Routes, RoutingError> {
+        return match self.route_legs(waypoints, |start, end| self.shortest_path(start, end, criteria, use_distance)) {
+            Some(d_r) => Ok(Routes {
+                paths: vec![d_r],
+                lower_bound: None,
+                exact: true,
+            }),
+            None => Err(RoutingError::NoPath)
+        };
+    }
+
+    /// error for a route without path within the limits, with the lowest elevation rise and descent of any path through the waypoints
+    fn get_infeasible_error(&self, waypoints: &[usize], criteria: Criteria, use_distance: bool) -> RoutingError {
+        let lowest = |rise_weight: f64, descent_weight: f64| {
+            let criteria = criteria.with_elevation_weights(rise_weight, descent_weight);
+            return self.route_legs(waypoints, |start, end| self.bidirectional_dijkstra(Dijkstra::Elevation, start, end, criteria, use_distance, Some(f64::MAX)));
+        };
+        return match (lowest(1.0, 0.0), lowest(0.0, 1.0)) {
+            (Some(min_ele_rise), Some(min_descent)) => RoutingError::ElevationInfeasible {
+                min_ele_rise: min_ele_rise.ele_rise,
+                min_descent: min_descent.descent,
+            },
+            _ => RoutingError::NoPath,
+        };
+    }
+
+    /// runs LARAC for the elevation rise, the descent or both
+    ///
+    /// With both limits the resource of LARAC is the rise plus the weighted descent and its limit the weighted sum of both limits.
+    /// The descent weight grows while the best path of a run descends too much and is bisected once a run rises too much.
+    /// Every run gives a lower bound, as every path within both limits is within the weighted sum of them.
+    fn larac<'a>(&self, waypoints: &[usize], criteria: Criteria<'a>, use_distance: bool, max_elevation: Option<f64>, max_descent: Option<f64>, all_paths: bool) -> Result<Larac<'a>, RoutingError> {
+        let is_feasible = |result: &DijkstraResult| {
+            max_elevation.is_none_or(|max_elevation| result.ele_rise <= max_elevation) && max_descent.is_none_or(|max_descent| result.descent <= max_descent)
+        };
+        let (max_elevation, max_descent) = match (max_elevation, max_descent) {
+            (Some(max_elevation), Some(max_descent)) => (max_elevation, max_descent),
+            (Some(max_elevation), None) => return self.larac_run(waypoints, criteria.with_elevation_weights(1.0, 0.0), use_distance, max_elevation, &is_feasible, all_paths),
+            (None, Some(max_descent)) => return self.larac_run(waypoints, criteria.with_elevation_weights(0.0, 1.0), use_distance, max_descent, &is_feasible, all_paths),
+            (None, None) => return Err(RoutingError::InvalidRequest {
+                reason: "LARAC needs an elevation or descent limit".to_string(),
+            }),
+        };
+
+        let mut paths = Vec::<DijkstraResult>::new();
+        let mut lower_bound = 0.0;
+        let mut last_run = None;
+        let mut descent_weight = 0.0;
+        // descent weights of runs whose best path descended or rose too much
+        let mut too_low = 0.0;
+        let mut too_high: Option<f64> = None;
+        for _ in 0..DESCENT_WEIGHT_ROUNDS {
+            let run_criteria = criteria.with_elevation_weights(1.0, descent_weight);
+            let run = self.larac_run(waypoints, run_criteria, use_distance, max_elevation + descent_weight * max_descent, &is_feasible, true)?;
+            lower_bound = f64::max(lower_bound, run.lower_bound);
+            for path in run.paths.iter() {
+                if !paths.contains(path) {
+                    paths.push(path.clone());
+                }
+            }
+            let best = run.best.clone();
+            last_run = Some(run);
+            if best.descent > max_descent {
+                too_low = descent_weight;
+            } else if best.ele_rise > max_elevation {
+                too_high = Some(descent_weight);
+            } else {
+                break;
+            }
+            descent_weight = match too_high {
+                Some(too_high) => (too_low + too_high) / 2.0,
+                None if descent_weight == 0.0 => 1.0,
+                None => 2.0 * descent_weight,
+            };
+        }
+        let last_run = last_run.unwrap();
+        // the optimal path found is the last one like in a single run
+        paths.sort_by(|a, b| b.distance.partial_cmp(&a.distance).unwrap_or(Ordering::Equal));
+        let best = match paths.last() {
+            Some(best) => best.clone(),
+            None => return Err(RoutingError::NoPathWithinLimits),
+        };
+        if !all_paths {
+            paths = vec![best.clone()];
+        }
+        return Ok(Larac {
+            paths,
+            best,
+            criteria: last_run.criteria,
+            multiplier: last_run.multiplier,
+            limit: last_run.limit,
+            lower_bound,
+        });
+    }
+
+    /// LARAC for the elevation costs of the criteria, paths are only returned if they are feasible
+    ///
+    /// The weighted costs of a route are the sum of the weighted costs of its legs, so every search of LARAC searches the legs one after another.
+    fn larac_run<'a>(&self, waypoints: &[usize], criteria: Criteria<'a>, use_distance: bool, limit: f64, is_feasible: &dyn Fn(&DijkstraResult) -> bool, all_paths: bool) -> Result<Larac<'a>, RoutingError> {
+        let elevation = |result: &DijkstraResult| criteria.rise_weight * result.ele_rise + criteria.descent_weight * result.descent;
         // Multiplier on 0 = 100% weight on distance
-        let mut distance_result: DijkstraResult = match self.dijkstra(Dijkstra::Multiplier, start, end, travel_type, use_distance, Some(0.0)) {
+        let mut distance_result: DijkstraResult = match self.route_legs(waypoints, |start, end| self.shortest_path(start, end, criteria, use_distance)) {
             Some(d_r) => d_r,
-            None => return Err("No shortest path after distance was found".to_string())
+            None => return Err(RoutingError::NoPath)
         };
-        let mut elevation_result: DijkstraResult = match self.dijkstra(Dijkstra::Elevation, start, end, travel_type, use_distance, Some(std::f64::MAX)) {
+        // the elevation estimate of A* is weak, searching from both sides settles less nodes
+        let mut elevation_result: DijkstraResult = match self.route_legs(waypoints, |start, end| self.bidirectional_dijkstra(Dijkstra::Elevation, start, end, criteria, use_distance, Some(f64::MAX))) {
             Some(d_r) => d_r,
-            None => return Err("No shortest path after elevation was found".to_string())
+            None => return Err(RoutingError::NoPath)
         };
 
-        if !all_paths && distance_result.ele_rise < max_elevation {
+        // no path is shorter than the shortest one without limit
+        let mut lower_bound = distance_result.distance;
+        if !all_paths && elevation(&distance_result) <= limit && is_feasible(&distance_result) {
             // shortest path in ele range and only optimal route requested
-            return Ok(vec![distance_result]);
+            return Ok(Larac {
+                paths: vec![distance_result.clone()],
+                best: distance_result,
+                criteria,
+                multiplier: 0.0,
+                limit,
+                lower_bound,
+            });
         }
 
         let mut found_paths = Vec::<DijkstraResult>::new();
         //always return the shortest path as a reference to the user
-        if elevation_result.eq(&distance_result) {
+        if elevation_result.eq(&distance_result) && elevation(&distance_result) <= limit && is_feasible(&distance_result) {
             // there is only one solution that's also perfect
-            return Ok(found_paths);
+            return Ok(Larac {
+                paths: vec![distance_result.clone()],
+                best: distance_result,
+                criteria,
+                multiplier: 0.0,
+                limit,
+                lower_bound,
+            });
         }
-        if elevation_result.ele_rise < max_elevation {
-            // add shortest path by elevation to results
-            found_paths.push(elevation_result.clone());
+        if elevation(&elevation_result) <= limit {
+            if is_feasible(&elevation_result) {
+                // add shortest path by elevation to results
+                found_paths.push(elevation_result.clone());
+            }
         } else {
-            return Err(format!("There is no solution, min elevation is {}", elevation_result.ele_rise).to_string());
+            return Err(self.get_infeasible_error(waypoints, criteria, use_distance));
         }
 
-        if distance_result.ele_rise < max_elevation {
+        if elevation(&distance_result) <= limit && is_feasible(&distance_result) {
             // shortest path is in range, return path with lowest elevation and optimal path
             found_paths.push(distance_result.clone());
         }
@@ -249,19 +844,21 @@ impl Graph {
         let  recommendation_multiplier_threshold = 0.01;
         loop {
             previous_multiplier = multiplier;
-            multiplier = ((distance_result.distance - elevation_result.distance) as f64) / ((elevation_result.ele_rise - distance_result.ele_rise) as f64);
-            let latest_result: DijkstraResult = match self.dijkstra(Dijkstra::Multiplier, start, end, travel_type, use_distance, Some(multiplier)) {
+            multiplier = (distance_result.distance - elevation_result.distance) / (elevation(&elevation_result) - elevation(&distance_result));
+            let latest_result: DijkstraResult = match self.route_legs(waypoints, |start, end| self.a_star(Dijkstra::Multiplier, start, end, criteria, use_distance, Some(multiplier))) {
                 Some(d_r) => d_r,
                 None => break
             };
+            // every path within the limit costs at least the optimum of the weighted sum minus the weighted limit
+            lower_bound = lower_bound.max(latest_result.distance + multiplier * (elevation(&latest_result) - limit));
 
             if latest_result.multiplier == elevation_result.multiplier || latest_result.multiplier == distance_result.multiplier {
                 // perfect multiplier found
                 break;
             }
-            if latest_result.ele_rise <= max_elevation {
+            if elevation(&latest_result) <= limit {
                 let multi_delta = (multiplier - previous_multiplier).abs();
-                if multi_delta > recommendation_multiplier_threshold {
+                if multi_delta > recommendation_multiplier_threshold && is_feasible(&latest_result) {
                     // add path as recommendation (optimal path for different weighting)
                     found_paths.push(latest_result.clone());
                 }
@@ -273,105 +870,951 @@ impl Graph {
             }
         }
 
-        return match all_paths {
+        let paths = match all_paths {
             // return the optimal result (last one found)
             false => match found_paths.last() {
-                Some(result) => Ok(vec![result.clone()]),
-                None => return Err("Did not find an optimal path".to_string())
+                Some(result) => vec![result.clone()],
+                None => return Err(RoutingError::NoPathWithinLimits)
             }
             // return all paths found
-            true => Ok(found_paths)
+            true => found_paths
         };
+        return Ok(Larac {
+            paths,
+            best: elevation_result,
+            criteria,
+            multiplier,
+            limit,
+            lower_bound,
+        });
     }
 
-    fn dijkstra(&self, min_of: Dijkstra, start: usize, end: usize, travel_type: usize, use_distance: bool, multiplier: Option<f64>) -> Option<DijkstraResult> {
-        let mut dist = vec![(MAX_F64, None); self.nodes.len()];
+    /// returns the shortest path within max_elevation and max_descent, proven to be optimal
+    ///
+    /// LARAC provides the first upper bound and its last multiplier. A label setting search then only extends partial
+    /// paths whose lagrangian lower bound, computed with exact remaining costs of a backward search, is below the upper bound.
+    /// If the search exceeds label_limit labels, the best path of LARAC and its lower bound are returned instead.
+    /// With all_paths the recommendations of LARAC are returned as well, the optimal path is the last one.
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_exact_path(&self, waypoints: &[usize], criteria: Criteria, use_distance: bool, max_elevation: Option<f64>, max_descent: Option<f64>, all_paths: bool, label_limit: usize) -> Result<Routes, RoutingError> {
+        check_waypoints(waypoints)?;
+        if max_elevation.is_none() && max_descent.is_none() {
+            return self.find_shortest_route(waypoints, criteria, use_distance);
+        }
+        let larac = self.larac(waypoints, criteria, use_distance, max_elevation, max_descent, true)?;
+        let is_feasible = |result: &DijkstraResult| {
+            max_elevation.is_none_or(|max_elevation| result.ele_rise <= max_elevation) && max_descent.is_none_or(|max_descent| result.descent <= max_descent)
+        };
+        let best = larac.paths
+            .iter()
+            .filter(|result| is_feasible(result))
+            .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(Ordering::Equal))
+            .cloned();
+        let best = match best {
+            Some(best) => best,
+            None => return Err(RoutingError::NoPathWithinLimits),
+        };
+        let mut paths = if all_paths {
+            larac.paths.iter().filter(|result| **result != best).cloned().collect()
+        } else {
+            Vec::new()
+        };
+        if best.distance <= larac.lower_bound {
+            // LARAC already found the optimum
+            paths.push(best.clone());
+            return Ok(Routes {
+                paths,
+                lower_bound: Some(best.distance),
+                exact: true,
+            });
+        }
+        let limits = (max_elevation, max_descent);
+        return match self.constrained_search(waypoints, &larac, use_distance, limits, best.distance, label_limit) {
+            Ok(result) => {
+                let optimum = result.unwrap_or(best);
+                let lower_bound = optimum.distance;
+                paths.push(optimum);
+                Ok(Routes {
+                    paths,
+                    lower_bound: Some(lower_bound),
+                    exact: true,
+                })
+            }
+            // the best path of LARAC is not proven to be the optimum
+            Err(_) => {
+                paths.push(best);
+                Ok(Routes {
+                    paths,
+                    lower_bound: Some(larac.lower_bound),
+                    exact: false,
+                })
+            }
+        };
+    }
 
+    /// label setting search for a path within the limits of elevation rise and descent that is shorter than upper_bound
+    ///
+    /// Backward searches from every waypoint provide the lowest remaining elevation rise (or descent without rise limit) and
+    /// the lowest remaining weighted costs of the last LARAC run for every node and leg. A partial path is dropped if it can not stay
+    /// within the limits, if its lagrangian lower bound is not below the best path found so far or if another partial path
+    /// to the node in the same leg is at least as good in all costs.
+    #[allow(clippy::too_many_arguments)]
+    fn constrained_search(&self, waypoints: &[usize], larac: &Larac, use_distance: bool, limits: (Option<f64>, Option<f64>), upper_bound: f64, label_limit: usize) -> Result<Option<DijkstraResult>, RoutingError> {
+        let (max_elevation, max_descent) = limits;
+        let criteria = larac.criteria;
+        let multiplier = larac.multiplier;
+        let legs = waypoints.len() - 1;
+        let start = waypoints[0];
+        // the geometric lower bound of the descent, the drops between the waypoints after the current leg are added
+        let descent_tails = get_suffix_sums(waypoints.windows(2).map(|leg| self.get_elevation_difference(leg[1], leg[0])));
+        return self.with_workspace(|workspace| {
+            workspace.add_leg_labels(2 * legs, self.nodes.len());
+            let SearchWorkspace { leg_labels, forward_heap, .. } = workspace;
+            let (remaining_limited, remaining_costs) = leg_labels[..2 * legs].split_at_mut(legs);
+            // the exact remaining rise, or the descent if only the descent is limited
+            let limited_criteria = match max_elevation {
+                Some(_) => criteria.with_elevation_weights(1.0, 0.0),
+                None => criteria.with_elevation_weights(0.0, 1.0),
+            };
+            for leg in 0..legs {
+                self.backward_costs(&mut remaining_limited[leg], forward_heap, &Dijkstra::Elevation, waypoints[leg + 1], limited_criteria, use_distance, None);
+                self.backward_costs(&mut remaining_costs[leg], forward_heap, &Dijkstra::Multiplier, waypoints[leg + 1], criteria, use_distance, Some(multiplier));
+                if remaining_costs[leg].cost(waypoints[leg]) == MAX_F64 {
+                    return Ok(None);
+                }
+            }
+            let remaining_limited: &[Labels] = remaining_limited;
+            let remaining_costs: &[Labels] = remaining_costs;
+            let limited_tails = get_suffix_sums((0..legs).map(|leg| remaining_limited[leg].cost(waypoints[leg])));
+            let cost_tails = get_suffix_sums((0..legs).map(|leg| remaining_costs[leg].cost(waypoints[leg])));
+            // lowest costs from the node to the end, the path is in the given leg
+            let remaining = |labels: &[Labels], tails: &[f64], node: usize, leg: usize| match leg < legs {
+                true => labels[leg].cost(node) + tails[leg + 1],
+                false => 0.0,
+            };
+            // the lagrangian lower bound of a partial path, the weighted limit is subtracted when comparing with the upper bound
+            let bound = |node: usize, leg: usize, distance: f64, ele_rise: f64, descent: f64| {
+                distance + multiplier * (criteria.rise_weight * ele_rise + criteria.descent_weight * descent) + remaining(remaining_costs, &cost_tails, node, leg)
+            };
+            let weighted_limit = multiplier * larac.limit;
+            let exceeds_limits = |node: usize, leg: usize, ele_rise: f64, descent: f64| match (max_elevation, max_descent) {
+                (Some(max_elevation), max_descent) => {
+                    let remaining_descent = match leg < legs {
+                        true => self.get_elevation_difference(waypoints[leg + 1], node) + descent_tails[leg + 1],
+                        false => 0.0,
+                    };
+                    ele_rise + remaining(remaining_limited, &limited_tails, node, leg) > max_elevation
+                        || max_descent.is_some_and(|max_descent| descent + remaining_descent > max_descent)
+                }
+                (None, Some(max_descent)) => descent + remaining(remaining_limited, &limited_tails, node, leg) > max_descent,
+                (None, None) => false,
+            };
+
+            let mut upper_bound = upper_bound;
+            let mut best = None;
+            let start_leg = get_next_leg(waypoints, 0, start);
+            let mut labels = vec![ParetoLabel {
+                node: start,
+                leg: start_leg,
+                distance: 0.0,
+                ele_rise: 0.0,
+                descent: 0.0,
+                previous: None,
+                dominated: false,
+            }];
+            let mut bags = HashMap::<(usize, usize), Vec<usize>>::new();
+            bags.insert((start_leg, start), vec![0]);
+            let mut heap = BinaryHeap::new();
+            heap.push(ParetoState {
+                label: 0,
+                priority: bound(start, start_leg, 0.0, 0.0, 0.0),
+                ele_priority: 0.0,
+            });
+
+            while let Some(ParetoState { label, priority, .. }) = heap.pop() {
+                if priority - weighted_limit >= upper_bound {
+                    // the heap is ordered by the bound, no remaining label can lead to a shorter path
+                    break;
+                }
+                let ParetoLabel { node, leg, distance, ele_rise, descent, dominated, .. } = labels[label];
+                if dominated {
+                    continue;
+                }
+                if leg == legs {
+                    if distance < upper_bound {
+                        upper_bound = distance;
+                        best = Some(label);
+                    }
+                    continue;
+                }
+                for edge in self.offset[node] as usize..self.offset[node + 1] as usize {
+                    let current_way: Way = self.ways[edge];
+                    if !self.is_way_allowed(&current_way, &criteria) {
+                        continue;
+                    }
+                    let next_node = current_way.target as usize;
+                    let next_leg = get_next_leg(waypoints, leg, next_node);
+                    let next_distance = distance + self.get_edge_distance(current_way, &criteria, use_distance);
+                    let next_ele_rise = ele_rise + self.get_edge_elevation_rise(current_way);
+                    let next_descent = descent + self.get_edge_descent(current_way);
+                    let next_bound = bound(next_node, next_leg, next_distance, next_ele_rise, next_descent);
+                    if exceeds_limits(next_node, next_leg, next_ele_rise, next_descent)
+                        || next_bound - weighted_limit >= upper_bound
+                        || is_dominated(&labels, bags.get(&(next_leg, next_node)), next_distance, next_ele_rise, max_descent.map(|_| next_descent)) {
+                        continue;
+                    }
+                    if labels.len() >= label_limit {
+                        return Err(RoutingError::LabelLimitExceeded { limit: label_limit });
+                    }
+                    let id = labels.len();
+                    add_label(&mut labels, bags.entry((next_leg, next_node)).or_default(), ParetoLabel {
+                        node: next_node,
+                        leg: next_leg,
+                        distance: next_distance,
+                        ele_rise: next_ele_rise,
+                        descent: next_descent,
+                        previous: Some(label),
+                        dominated: false,
+                    }, max_descent.is_some());
+                    heap.push(ParetoState {
+                        label: id,
+                        priority: next_bound,
+                        ele_priority: next_ele_rise,
+                    });
+                }
+            }
+
+            return Ok(best.map(|id| {
+                let mut result = get_label_path(&labels, id);
+                result.multiplier = Some(multiplier);
+                result
+            }));
+        });
+    }
+
+    /// backward Dijkstra search from the target through the whole graph, the labels get the costs of every node to the target
+    #[allow(clippy::too_many_arguments)]
+    fn backward_costs(&self, costs: &mut Labels, heap: &mut BinaryHeap<State>, min_of: &Dijkstra, end: usize, criteria: Criteria, use_distance: bool, multiplier: Option<f64>) {
+        heap.clear();
+        costs.set(end, 0.0, None);
+        heap.push(State {
+            node: end,
+            cost: 0.0,
+            priority: 0.0,
+            distance: 0.0,
+            ele_rise: 0.0,
+            descent: 0.0,
+        });
+        while let Some(State { node, cost, .. }) = heap.pop() {
+            if cost > costs.cost(node) {
+                continue;
+            }
+            for edge in self.reverse_offset[node] as usize..self.reverse_offset[node + 1] as usize {
+                let current_way: Way = self.ways[self.reverse_way_ids[edge] as usize];
+                if !self.is_way_allowed(&current_way, &criteria) {
+                    continue;
+                }
+                let additional_distance = self.get_edge_distance(current_way, &criteria, use_distance);
+                let next_cost = cost + self.get_edge_cost(min_of, additional_distance, self.get_edge_elevation(current_way, &criteria), multiplier);
+                let next_node = current_way.source as usize;
+                if next_cost < costs.cost(next_node) {
+                    costs.set(next_node, next_cost, None);
+                    heap.push(State {
+                        node: next_node,
+                        cost: next_cost,
+                        priority: next_cost,
+                        distance: 0.0,
+                        ele_rise: 0.0,
+                        descent: 0.0,
+                    });
+                }
+            }
+        }
+    }
+
+    /// returns all pareto optimal paths within max_elevation and max_descent sorted by distance, or only the shortest of them
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_pareto_paths(&self, waypoints: &[usize], criteria: Criteria, use_distance: bool, max_elevation: Option<f64>, max_descent: Option<f64>, all_paths: bool) -> Result<Routes, RoutingError> {
+        check_waypoints(waypoints)?;
+        let mut paths = self.pareto_search(waypoints, criteria, use_distance, (max_elevation, max_descent), LABEL_LIMIT)?;
+        if paths.is_empty() {
+            // the pareto search is exact, so there is no path within the limits
+            return Err(self.get_infeasible_error(waypoints, criteria, use_distance));
+        }
+        // the shortest pareto optimal path within the limits is the optimum
+        let lower_bound = max_elevation.or(max_descent).map(|_| paths[0].distance);
+        if !all_paths {
+            paths.truncate(1);
+        }
+        return Ok(Routes {
+            paths,
+            lower_bound,
+            exact: true,
+        });
+    }
+
+    /// exact bicriteria search for the pareto frontier of distance (or time) and elevation rise of a route through the waypoints
+    ///
+    /// Every node keeps the labels of its partial paths that are not dominated by another one in the same leg. Labels are pruned if their
+    /// costs plus the A* lower bounds are dominated by a path to the target or exceed the limits of elevation rise and descent.
+    /// With a descent limit the descent is a third cost of the labels, the frontier only contains paths within the limit.
+    /// Fails if more than label_limit labels are created.
+    pub fn pareto_search(&self, waypoints: &[usize], criteria: Criteria, use_distance: bool, limits: (Option<f64>, Option<f64>), label_limit: usize) -> Result<Vec<DijkstraResult>, RoutingError> {
+        check_waypoints(waypoints)?;
+        let max_elevation = limits.0.unwrap_or(MAX_F64);
+        let max_descent = limits.1;
+        let legs = waypoints.len() - 1;
+        let start = waypoints[0];
+        let end = waypoints[legs];
+        let (distance_factor, _) = self.get_estimate_factors(&Dijkstra::Multiplier, criteria, use_distance, Some(0.0));
+        // lower bounds of the distance, elevation rise and descent between two nodes
+        let direct_estimate = |from: usize, to: usize| -> (f64, f64, f64) {
+            let (from_node, to_node) = (&self.nodes[from], &self.nodes[to]);
+            let distance = calc_distance(from_node.latitude, from_node.longitude, to_node.latitude, to_node.longitude) as f64;
+            return (distance_factor * distance, self.get_elevation_difference(from, to), self.get_elevation_difference(to, from));
+        };
+        let distance_tails = get_suffix_sums(waypoints.windows(2).map(|leg| direct_estimate(leg[0], leg[1]).0));
+        let rise_tails = get_suffix_sums(waypoints.windows(2).map(|leg| direct_estimate(leg[0], leg[1]).1));
+        let descent_tails = get_suffix_sums(waypoints.windows(2).map(|leg| direct_estimate(leg[0], leg[1]).2));
+        let estimate = |node_id: usize, leg: usize| -> (f64, f64, f64) {
+            if leg == legs {
+                return (0.0, 0.0, 0.0);
+            }
+            let (distance, ele_rise, descent) = direct_estimate(node_id, waypoints[leg + 1]);
+            return (
+                ESTIMATE_SLACK * (distance + distance_tails[leg + 1]),
+                ESTIMATE_SLACK * (ele_rise + rise_tails[leg + 1]),
+                ESTIMATE_SLACK * (descent + descent_tails[leg + 1]),
+            );
+        };
+
+        let start_leg = get_next_leg(waypoints, 0, start);
+        let mut labels = vec![ParetoLabel {
+            node: start,
+            leg: start_leg,
+            distance: 0.0,
+            ele_rise: 0.0,
+            descent: 0.0,
+            previous: None,
+            dominated: false,
+        }];
+        // labels of every reached node and leg that are not dominated
+        let mut bags = HashMap::<(usize, usize), Vec<usize>>::new();
+        bags.insert((start_leg, start), vec![0]);
         let mut heap = BinaryHeap::new();
-        dist[start] = (0.0, None);
+        let (distance_estimate, ele_estimate, _) = estimate(start, start_leg);
+        heap.push(ParetoState {
+            label: 0,
+            priority: distance_estimate,
+            ele_priority: ele_estimate,
+        });
+
+        while let Some(ParetoState { label, .. }) = heap.pop() {
+            let ParetoLabel { node, leg, distance, ele_rise, descent, dominated, .. } = labels[label];
+            // paths to the target are not extended
+            if dominated || leg == legs {
+                continue;
+            }
+            for edge in self.offset[node] as usize..self.offset[node + 1] as usize {
+                let current_way: Way = self.ways[edge];
+                if !self.is_way_allowed(&current_way, &criteria) {
+                    continue;
+                }
+                let next_node = current_way.target as usize;
+                let next_leg = get_next_leg(waypoints, leg, next_node);
+                let next_distance = distance + self.get_edge_distance(current_way, &criteria, use_distance);
+                let next_ele_rise = ele_rise + self.get_edge_elevation_rise(current_way);
+                let next_descent = descent + self.get_edge_descent(current_way);
+                let (distance_estimate, ele_estimate, descent_estimate) = estimate(next_node, next_leg);
+                // the descent only counts for the dominance if it is limited
+                let compared_descent = max_descent.map(|_| next_descent);
+                if next_ele_rise + ele_estimate > max_elevation
+                    || max_descent.is_some_and(|max_descent| next_descent + descent_estimate > max_descent)
+                    || is_dominated(&labels, bags.get(&(legs, end)), next_distance + distance_estimate, next_ele_rise + ele_estimate, None)
+                    || is_dominated(&labels, bags.get(&(next_leg, next_node)), next_distance, next_ele_rise, compared_descent) {
+                    continue;
+                }
+                if labels.len() >= label_limit {
+                    return Err(RoutingError::LabelLimitExceeded { limit: label_limit });
+                }
+                let id = labels.len();
+                add_label(&mut labels, bags.entry((next_leg, next_node)).or_default(), ParetoLabel {
+                    node: next_node,
+                    leg: next_leg,
+                    distance: next_distance,
+                    ele_rise: next_ele_rise,
+                    descent: next_descent,
+                    previous: Some(label),
+                    dominated: false,
+                }, max_descent.is_some());
+                heap.push(ParetoState {
+                    label: id,
+                    priority: next_distance + distance_estimate,
+                    ele_priority: next_ele_rise + ele_estimate,
+                });
+            }
+        }
+
+        let mut frontier = bags.remove(&(legs, end)).unwrap_or_default();
+        frontier.sort_by(|&a, &b| {
+            labels[a].distance.partial_cmp(&labels[b].distance)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| labels[a].ele_rise.partial_cmp(&labels[b].ele_rise).unwrap_or(Ordering::Equal))
+        });
+        let mut results = Vec::<DijkstraResult>::with_capacity(frontier.len());
+        for id in frontier {
+            // with the descent as third cost, the target may keep paths that are dominated in distance and elevation rise
+            if results.last().is_some_and(|last| last.ele_rise <= labels[id].ele_rise) {
+                continue;
+            }
+            results.push(get_label_path(&labels, id));
+        }
+        return Ok(results);
+    }
+
+    /// one to all search from start, returns every node reachable within max_cost (in km or h) in the order they were settled
+    ///
+    /// Unlike the searches for routes it does not stop at a target and keeps the labels of all settled nodes. With max_elevation
+    /// only paths within the elevation rise count, the cheapest of them needs a bicriteria search like pareto_search.
+    /// Fails if that search creates more than label_limit labels.
+    pub fn find_reachable(&self, start: usize, criteria: Criteria, use_distance: bool, max_cost: f64, max_elevation: Option<f64>, label_limit: usize) -> Result<Vec<Reachable>, RoutingError> {
+        return match max_elevation {
+            Some(max_elevation) => self.reachable_within_elevation(start, criteria, use_distance, max_cost, max_elevation, label_limit),
+            None => Ok(self.with_workspace(|workspace| self.settle_all_in(workspace, start, criteria, use_distance, max_cost))),
+        };
+    }
+
+    /// Dijkstra search that settles every node within max_cost instead of stopping at a target
+    fn settle_all_in(&self, workspace: &mut SearchWorkspace<State>, start: usize, criteria: Criteria, use_distance: bool, max_cost: f64) -> Vec<Reachable> {
+        let SearchWorkspace { forward: dist, forward_heap: heap, .. } = workspace;
+        let mut settled = Vec::<Reachable>::new();
+        dist.set(start, 0.0, None);
         heap.push(State {
             node: start,
             cost: 0.0,
+            priority: 0.0,
+            distance: 0.0,
+            ele_rise: 0.0,
+            descent: 0.0,
+        });
+        while let Some(State { node, cost, ele_rise, .. }) = heap.pop() {
+            if cost > dist.cost(node) {
+                continue;
+            }
+            settled.push(Reachable {
+                node,
+                cost,
+                ele_rise,
+            });
+            for edge in self.offset[node] as usize..self.offset[node + 1] as usize {
+                let current_way: Way = self.ways[edge];
+                if !self.is_way_allowed(&current_way, &criteria) {
+                    continue;
+                }
+                let next_node = current_way.target as usize;
+                let next_cost = cost + self.get_edge_distance(current_way, &criteria, use_distance);
+                if next_cost <= max_cost && next_cost < dist.cost(next_node) {
+                    dist.set(next_node, next_cost, Some(node));
+                    heap.push(State {
+                        node: next_node,
+                        cost: next_cost,
+                        priority: next_cost,
+                        distance: next_cost,
+                        ele_rise: ele_rise + self.get_edge_elevation_rise(current_way),
+                        descent: 0.0,
+                    });
+                }
+            }
+        }
+        return settled;
+    }
+
+    /// bicriteria one to all search, a node is reachable with the cost of its first label taken from the heap
+    ///
+    /// The heap is ordered by cost, so later labels of the node are more expensive. They are still extended if they rise less,
+    /// because a path with less elevation rise may reach other nodes within max_elevation.
+    fn reachable_within_elevation(&self, start: usize, criteria: Criteria, use_distance: bool, max_cost: f64, max_elevation: f64, label_limit: usize) -> Result<Vec<Reachable>, RoutingError> {
+        let mut labels = vec![ParetoLabel {
+            node: start,
+            leg: 0,
             distance: 0.0,
             ele_rise: 0.0,
+            descent: 0.0,
+            previous: None,
+            dominated: false,
+        }];
+        // labels of every reached node that are not dominated
+        let mut bags = HashMap::<usize, Vec<usize>>::new();
+        bags.insert(start, vec![0]);
+        let mut heap = BinaryHeap::new();
+        heap.push(ParetoState {
+            label: 0,
+            priority: 0.0,
+            ele_priority: 0.0,
         });
+        let mut reachable = Vec::<Reachable>::new();
+        let mut reached = HashSet::<usize>::new();
 
-        while let Some(State { node, cost, distance, ele_rise }) = heap.pop() {
-            if node == end {
-                let mut path = Vec::with_capacity(dist.len() / 2);
-                let mut current_dist = dist[end];
-                path.push(end);
-                while let Some(prev) = current_dist.1 {
-                    path.push(prev);
-                    current_dist = dist[prev];
+        while let Some(ParetoState { label, .. }) = heap.pop() {
+            let ParetoLabel { node, distance, ele_rise, dominated, .. } = labels[label];
+            if dominated {
+                continue;
+            }
+            if reached.insert(node) {
+                reachable.push(Reachable {
+                    node,
+                    cost: distance,
+                    ele_rise,
+                });
+            }
+            for edge in self.offset[node] as usize..self.offset[node + 1] as usize {
+                let current_way: Way = self.ways[edge];
+                if !self.is_way_allowed(&current_way, &criteria) {
+                    continue;
                 }
-                path.reverse();
+                let next_node = current_way.target as usize;
+                let next_distance = distance + self.get_edge_distance(current_way, &criteria, use_distance);
+                let next_ele_rise = ele_rise + self.get_edge_elevation_rise(current_way);
+                if next_distance > max_cost
+                    || next_ele_rise > max_elevation
+                    || is_dominated(&labels, bags.get(&next_node), next_distance, next_ele_rise, None) {
+                    continue;
+                }
+                if labels.len() >= label_limit {
+                    return Err(RoutingError::LabelLimitExceeded { limit: label_limit });
+                }
+                let id = labels.len();
+                add_label(&mut labels, bags.entry(next_node).or_default(), ParetoLabel {
+                    node: next_node,
+                    leg: 0,
+                    distance: next_distance,
+                    ele_rise: next_ele_rise,
+                    descent: 0.0,
+                    previous: Some(label),
+                    dominated: false,
+                }, false);
+                heap.push(ParetoState {
+                    label: id,
+                    priority: next_distance,
+                    ele_priority: next_ele_rise,
+                });
+            }
+        }
+        return Ok(reachable);
+    }
+
+    /// cheapest paths from start to every target with a single Dijkstra search, None for targets that can not be reached
+    ///
+    /// The search stops once all targets are settled, so a row of a distance matrix costs about as much as the search for its
+    /// most distant target.
+    pub fn find_one_to_many(&self, start: usize, targets: &[usize], criteria: Criteria, use_distance: bool) -> Vec<Option<DijkstraResult>> {
+        return self.with_workspace(|workspace| self.one_to_many_in(workspace, start, targets, criteria, use_distance));
+    }
+
+    fn one_to_many_in(&self, workspace: &mut SearchWorkspace<State>, start: usize, targets: &[usize], criteria: Criteria, use_distance: bool) -> Vec<Option<DijkstraResult>> {
+        let SearchWorkspace { forward: dist, forward_heap: heap, .. } = workspace;
+        // targets that are not settled yet with their costs once they are
+        let mut remaining: HashMap<usize, Option<(f64, f64, f64)>> = targets.iter().map(|&target| (target, None)).collect();
+        let mut amount_remaining = remaining.len();
+        dist.set(start, 0.0, None);
+        heap.push(State {
+            node: start,
+            cost: 0.0,
+            priority: 0.0,
+            distance: 0.0,
+            ele_rise: 0.0,
+            descent: 0.0,
+        });
+        while let Some(State { node, cost, ele_rise, descent, .. }) = heap.pop() {
+            if cost > dist.cost(node) {
+                continue;
+            }
+            if let Some(costs @ None) = remaining.get_mut(&node) {
+                *costs = Some((cost, ele_rise, descent));
+                amount_remaining -= 1;
+                if amount_remaining == 0 {
+                    break;
+                }
+            }
+            for edge in self.offset[node] as usize..self.offset[node + 1] as usize {
+                let current_way: Way = self.ways[edge];
+                if !self.is_way_allowed(&current_way, &criteria) {
+                    continue;
+                }
+                let next_node = current_way.target as usize;
+                let next_cost = cost + self.get_edge_distance(current_way, &criteria, use_distance);
+                if next_cost < dist.cost(next_node) {
+                    dist.set(next_node, next_cost, Some(node));
+                    heap.push(State {
+                        node: next_node,
+                        cost: next_cost,
+                        priority: next_cost,
+                        distance: next_cost,
+                        ele_rise: ele_rise + self.get_edge_elevation_rise(current_way),
+                        descent: descent + self.get_edge_descent(current_way),
+                    });
+                }
+            }
+        }
+        return targets
+            .iter()
+            .map(|target| {
+                remaining[target].map(|(distance, ele_rise, descent)| DijkstraResult {
+                    path: get_predecessor_path(dist, *target),
+                    distance,
+                    ele_rise,
+                    descent,
+                    multiplier: None,
+                })
+            })
+            .collect();
+    }
+
+    /// searches the legs between the waypoints one after another and concatenates their paths, the joint nodes are only contained once
+    fn route_legs<F: Fn(usize, usize) -> Option<DijkstraResult>>(&self, waypoints: &[usize], search: F) -> Option<DijkstraResult> {
+        let mut route: Option<DijkstraResult> = None;
+        for leg in waypoints.windows(2) {
+            let result = search(leg[0], leg[1])?;
+            route = Some(match route {
+                Some(mut route) => {
+                    route.path.extend_from_slice(&result.path[1..]);
+                    route.distance += result.distance;
+                    route.ele_rise += result.ele_rise;
+                    route.descent += result.descent;
+                    route
+                }
+                None => result,
+            });
+        }
+        return route;
+    }
+
+    /// shortest path by distance or time, uses the contraction hierarchy created by pre if there is one and no ways are excluded by their steepness
+    pub fn shortest_path(&self, start: usize, end: usize, criteria: Criteria, use_distance: bool) -> Option<DijkstraResult> {
+        let hierarchy = self.hierarchies.iter().find(|ch| ch.travel_type == criteria.travel_type as u32 && ch.by_distance == use_distance && criteria.filters_by_travel_type_only());
+        return match hierarchy {
+            Some(ch) => self.ch_query(ch, start, end, criteria, use_distance),
+            None => self.a_star(Dijkstra::Multiplier, start, end, criteria, use_distance, Some(0.0)),
+        };
+    }
+
+    /// bidirectional search that only goes up in the hierarchy, the shortcuts of the result are unpacked afterwards
+    pub fn ch_query(&self, ch: &ContractionHierarchy, start: usize, end: usize, criteria: Criteria, use_distance: bool) -> Option<DijkstraResult> {
+        return self.with_workspace(|workspace| self.ch_query_in(workspace, ch, start, end, criteria, use_distance));
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn ch_query_in(&self, workspace: &mut SearchWorkspace<State>, ch: &ContractionHierarchy, start: usize, end: usize, criteria: Criteria, use_distance: bool) -> Option<DijkstraResult> {
+        // cost and previous node, next node for the backward search
+        let SearchWorkspace { forward, backward, forward_heap, backward_heap, .. } = workspace;
+        let initial_state = |node: usize| State {
+            node,
+            cost: 0.0,
+            priority: 0.0,
+            distance: 0.0,
+            ele_rise: 0.0,
+            descent: 0.0,
+        };
+        forward.set(start, 0.0, None);
+        backward.set(end, 0.0, None);
+        forward_heap.push(initial_state(start));
+        backward_heap.push(initial_state(end));
+
+        let mut best_cost = if start == end { 0.0 } else { MAX_F64 };
+        let mut meeting_node = if start == end { Some(start) } else { None };
+        loop {
+            // both searches only go up, so each one may stop on its own once it can not improve the result
+            let forward_min = forward_heap.peek().map(|state: &State| state.cost).filter(|&cost| cost < best_cost);
+            let backward_min = backward_heap.peek().map(|state: &State| state.cost).filter(|&cost| cost < best_cost);
+            let is_forward = match (forward_min, backward_min) {
+                (None, None) => break,
+                (Some(forward_min), Some(backward_min)) => forward_min <= backward_min,
+                (forward_min, _) => forward_min.is_some(),
+            };
+            let (heap, costs, other_costs, edges, offset) = if is_forward {
+                (&mut *forward_heap, &mut *forward, &*backward, &ch.up, &ch.up_offset)
+            } else {
+                (&mut *backward_heap, &mut *backward, &*forward, &ch.down, &ch.down_offset)
+            };
+            let State { node, cost, .. } = heap.pop().unwrap();
+            if cost > costs.cost(node) {
+                continue;
+            }
+            for edge in edges[offset[node] as usize..offset[node + 1] as usize].iter() {
+                let next_node = edge.neighbor as usize;
+                let next_cost = cost + edge.weight;
+                if next_cost < costs.cost(next_node) {
+                    costs.set(next_node, next_cost, Some(node));
+                    heap.push(State {
+                        node: next_node,
+                        cost: next_cost,
+                        priority: next_cost,
+                        distance: 0.0,
+                        ele_rise: 0.0,
+                        descent: 0.0,
+                    });
+                    if next_cost + other_costs.cost(next_node) < best_cost {
+                        best_cost = next_cost + other_costs.cost(next_node);
+                        meeting_node = Some(next_node);
+                    }
+                }
+            }
+        }
+
+        let meeting_node = meeting_node?;
+        let mut ch_path = vec![meeting_node];
+        while let Some(prev) = forward.get(*ch_path.last().unwrap()).1 {
+            ch_path.push(prev);
+        }
+        ch_path.reverse();
+        while let Some(next) = backward.get(*ch_path.last().unwrap()).1 {
+            ch_path.push(next);
+        }
+
+        let mut path = vec![start];
+        for pair in ch_path.windows(2) {
+            // the unpacked part starts with the last node of the path
+            path.pop();
+            ch.unpack(pair[0], pair[1], &mut path);
+        }
+        // sum up the cheapest ways in path order like the other searches
+        let mut distance = 0.0;
+        let mut ele_rise = 0.0;
+        let mut descent = 0.0;
+        for pair in path.windows(2) {
+            let way = self.get_way(pair[0], pair[1], &criteria, use_distance)?;
+            distance += self.get_edge_distance(way, &criteria, use_distance);
+            ele_rise += self.get_edge_elevation_rise(way);
+            descent += self.get_edge_descent(way);
+        }
+        return Some(DijkstraResult {
+            path,
+            distance,
+            ele_rise,
+            descent,
+            multiplier: Some(0.0),
+        });
+    }
+
+    /// A* search, the haversine distance and the elevation difference to the target are lower bounds of the remaining costs
+    pub fn a_star(&self, min_of: Dijkstra, start: usize, end: usize, criteria: Criteria, use_distance: bool, multiplier: Option<f64>) -> Option<DijkstraResult> {
+        return self.search(min_of, start, end, criteria, use_distance, multiplier, true);
+    }
+
+    /// plain Dijkstra search, settles every node closer than the target. Reference for tests and benchmarks
+    #[cfg(test)]
+    pub fn dijkstra(&self, min_of: Dijkstra, start: usize, end: usize, criteria: Criteria, use_distance: bool, multiplier: Option<f64>) -> Option<DijkstraResult> {
+        return self.search(min_of, start, end, criteria, use_distance, multiplier, false);
+    }
+
+    /// returns the factors of the distance in km and the elevation rise in m for the estimated remaining costs
+    fn get_estimate_factors(&self, min_of: &Dijkstra, criteria: Criteria, use_distance: bool, multiplier: Option<f64>) -> (f64, f64) {
+        return match min_of {
+            Dijkstra::Elevation => (0.0, 1.0),
+            Dijkstra::Multiplier => {
+                let distance_factor = if use_distance {
+                    1.0
+                } else {
+                    1.0 / self.get_max_speed(&criteria)
+                };
+                // a negative multiplier would make the elevation part an upper bound
+                (distance_factor, multiplier.unwrap_or(0.0).max(0.0))
+            }
+        };
+    }
+
+    /// runs the search with the workspace of the current thread, its labels and heaps are reset before and extra leg labels freed after
+    fn with_workspace<R, F: FnOnce(&mut SearchWorkspace<State>) -> R>(&self, search: F) -> R {
+        return WORKSPACE.with(|workspace| {
+            let mut workspace = workspace.borrow_mut();
+            workspace.reset(self.nodes.len());
+            let result = search(&mut workspace);
+            workspace.release_leg_labels();
+            return result;
+        });
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn search(&self, min_of: Dijkstra, start: usize, end: usize, criteria: Criteria, use_distance: bool, multiplier: Option<f64>, use_estimate: bool) -> Option<DijkstraResult> {
+        return self.with_workspace(|workspace| self.search_in(workspace, min_of, start, end, criteria, use_distance, multiplier, use_estimate));
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn search_in(&self, workspace: &mut SearchWorkspace<State>, min_of: Dijkstra, start: usize, end: usize, criteria: Criteria, use_distance: bool, multiplier: Option<f64>, use_estimate: bool) -> Option<DijkstraResult> {
+        let SearchWorkspace { forward: dist, forward_heap: heap, .. } = workspace;
+
+        let target = self.nodes[end];
+        let (distance_factor, elevation_factor) = self.get_estimate_factors(&min_of, criteria, use_distance, multiplier);
+        let estimate = |node_id: usize| -> f64 {
+            if !use_estimate {
+                return 0.0;
+            }
+            let node = &self.nodes[node_id];
+            let distance = calc_distance(node.latitude, node.longitude, target.latitude, target.longitude) as f64;
+            let ele_rise = (target.elevation - node.elevation).max(0.0) as f64;
+            let descent = (node.elevation - target.elevation).max(0.0) as f64;
+            return ESTIMATE_SLACK * (distance_factor * distance + elevation_factor * (criteria.rise_weight * ele_rise + criteria.descent_weight * descent));
+        };
+
+        dist.set(start, 0.0, None);
+        heap.push(State {
+            node: start,
+            cost: 0.0,
+            priority: estimate(start),
+            distance: 0.0,
+            ele_rise: 0.0,
+            descent: 0.0,
+        });
+
+        while let Some(State { node, cost, distance, ele_rise, descent, .. }) = heap.pop() {
+            if node == end {
                 return Some(DijkstraResult {
-                    path,
+                    path: get_predecessor_path(dist, end),
                     distance,
                     ele_rise,
-                    multiplier: multiplier,
+                    descent,
+                    multiplier,
                 });
             }
-            if cost > dist[node].0 as f64 {
+            if cost > dist.cost(node) {
                 continue;
             }
-            for edge in self.offset[node]..self.offset[node + 1] {
+            for edge in self.offset[node] as usize..self.offset[node + 1] as usize {
                 let current_way: Way = self.ways[edge];
                 // skip way, if the type does not match
-                match travel_type {
-                    0 => match current_way.travel_type {
-                        0 | 1 | 5 => (),
-                        _ => continue,
-                    },
-                    1 => match current_way.travel_type {
-                        1 | 2 | 3 | 5 => (),
-                        _ => continue,
-                    },
-                    2 => match current_way.travel_type {
-                        3 | 4 | 5 => (),
-                        _ => continue,
-                    },
-                    _ => unreachable!(),
+                if !self.is_way_allowed(&current_way, &criteria) {
+                    continue;
                 }
                 // calculate costs
-                let additional_distance = self.get_edge_distance(current_way, travel_type, use_distance);
+                let additional_distance = self.get_edge_distance(current_way, &criteria, use_distance);
                 let additional_ele_rise = self.get_edge_elevation_rise(current_way);
-                let next = State {
-                    node: current_way.target,
-                    cost: cost + match min_of {
-                        Dijkstra::Elevation => additional_ele_rise,
-                        Dijkstra::Multiplier => match multiplier {
-                            Some(multiplier) => self.get_weight_with_multiplier(additional_distance, additional_ele_rise, multiplier),
-                            None => panic!("Dijkstra was called in multiplier mode but no multiplier was provided")
-                        }
-                    },
+                let additional_elevation = self.get_edge_elevation(current_way, &criteria);
+                let mut next = State {
+                    node: current_way.target as usize,
+                    priority: 0.0,
+                    cost: cost + self.get_edge_cost(&min_of, additional_distance, additional_elevation, multiplier),
                     distance: distance + additional_distance,
                     ele_rise: ele_rise + additional_ele_rise,
+                    descent: descent + self.get_edge_descent(current_way),
                 };
 
                 // add way to heap
-                if next.cost < dist[next.node].0 as f64 {
-                    dist[next.node] = (next.cost as f64, Some(node));
+                if next.cost < dist.cost(next.node) {
+                    dist.set(next.node, next.cost, Some(node));
+                    next.priority = next.cost + estimate(next.node);
                     heap.push(next);
                 }
             }
         }
-        None
+        return None;
     }
-}
 
+    /// Dijkstra search from start and end at the same time, the backward search uses the incoming ways
+    /// stops as soon as the two searches can not find a cheaper connection than the best one so far
+    pub fn bidirectional_dijkstra(&self, min_of: Dijkstra, start: usize, end: usize, criteria: Criteria, use_distance: bool, multiplier: Option<f64>) -> Option<DijkstraResult> {
+        return self.with_workspace(|workspace| self.bidirectional_dijkstra_in(workspace, min_of, start, end, criteria, use_distance, multiplier));
+    }
 
-/// get distance on earth surface using haversine formula
-fn calc_distance(lat_1: f32, long_1: f32, lat_2: f32, long_2: f32) -> f32 {
-    let r: f32 = 6371.0; // constant used for meters
-    let d_lat: f32 = (lat_2 - lat_1).to_radians();
-    let d_lon: f32 = (long_2 - long_1).to_radians();
-    let lat1: f32 = (lat_1).to_radians();
-    let lat2: f32 = (lat_2).to_radians();
+    #[allow(clippy::too_many_arguments)]
+    fn bidirectional_dijkstra_in(&self, workspace: &mut SearchWorkspace<State>, min_of: Dijkstra, start: usize, end: usize, criteria: Criteria, use_distance: bool, multiplier: Option<f64>) -> Option<DijkstraResult> {
+        // cost and the way the node was reached with, outgoing way for the backward search
+        let SearchWorkspace { forward, backward, forward_heap, backward_heap, .. } = workspace;
+        let initial_state = |node: usize| State {
+            node,
+            cost: 0.0,
+            priority: 0.0,
+            distance: 0.0,
+            ele_rise: 0.0,
+            descent: 0.0,
+        };
+        forward.set(start, 0.0, None);
+        backward.set(end, 0.0, None);
+        forward_heap.push(initial_state(start));
+        backward_heap.push(initial_state(end));
 
-    let a: f32 = ((d_lat / 2.0).sin()) * ((d_lat / 2.0).sin())
-        + ((d_lon / 2.0).sin()) * ((d_lon / 2.0).sin()) * (lat1.cos()) * (lat2.cos());
-    let c: f32 = 2.0 * ((a.sqrt()).atan2((1.0 - a).sqrt()));
-    return r * c;
+        let mut best_cost = if start == end { 0.0 } else { MAX_F64 };
+        let mut meeting_node = if start == end { Some(start) } else { None };
+        loop {
+            let forward_min = forward_heap.peek().map(|state: &State| state.cost).unwrap_or(MAX_F64);
+            let backward_min = backward_heap.peek().map(|state: &State| state.cost).unwrap_or(MAX_F64);
+            if forward_min + backward_min >= best_cost {
+                break;
+            }
+            let is_forward = forward_min <= backward_min;
+            let (heap, costs, other_costs) = if is_forward {
+                (&mut *forward_heap, &mut *forward, &*backward)
+            } else {
+                (&mut *backward_heap, &mut *backward, &*forward)
+            };
+            let State { node, cost, .. } = heap.pop().unwrap();
+            if cost > costs.cost(node) {
+                continue;
+            }
+            let edges = if is_forward {
+                self.offset[node] as usize..self.offset[node + 1] as usize
+            } else {
+                self.reverse_offset[node] as usize..self.reverse_offset[node + 1] as usize
+            };
+            for edge in edges {
+                let way_id = if is_forward { edge } else { self.reverse_way_ids[edge] as usize };
+                let current_way: Way = self.ways[way_id];
+                if !self.is_way_allowed(&current_way, &criteria) {
+                    continue;
+                }
+                let additional_distance = self.get_edge_distance(current_way, &criteria, use_distance);
+                let next_cost = cost + self.get_edge_cost(&min_of, additional_distance, self.get_edge_elevation(current_way, &criteria), multiplier);
+                let next_node = if is_forward { current_way.target } else { current_way.source } as usize;
+                if next_cost < costs.cost(next_node) {
+                    costs.set(next_node, next_cost, Some(way_id));
+                    heap.push(State {
+                        node: next_node,
+                        cost: next_cost,
+                        priority: next_cost,
+                        distance: 0.0,
+                        ele_rise: 0.0,
+                        descent: 0.0,
+                    });
+                    if next_cost + other_costs.cost(next_node) < best_cost {
+                        best_cost = next_cost + other_costs.cost(next_node);
+                        meeting_node = Some(next_node);
+                    }
+                }
+            }
+        }
+
+        let meeting_node = meeting_node?;
+        let mut way_ids = Vec::<usize>::new();
+        let mut node = meeting_node;
+        while let Some(way_id) = forward.get(node).1 {
+            way_ids.push(way_id);
+            node = self.ways[way_id].source as usize;
+        }
+        way_ids.reverse();
+        node = meeting_node;
+        while let Some(way_id) = backward.get(node).1 {
+            way_ids.push(way_id);
+            node = self.ways[way_id].target as usize;
+        }
+
+        // sum up in path order like the one directional search to get the same values
+        let mut path = Vec::with_capacity(way_ids.len() + 1);
+        path.push(start);
+        let mut distance = 0.0;
+        let mut ele_rise = 0.0;
+        let mut descent = 0.0;
+        for way_id in way_ids {
+            let way = self.ways[way_id];
+            distance += self.get_edge_distance(way, &criteria, use_distance);
+            ele_rise += self.get_edge_elevation_rise(way);
+            descent += self.get_edge_descent(way);
+            path.push(way.target as usize);
+        }
+        return Some(DijkstraResult {
+            path,
+            distance,
+            ele_rise,
+            descent,
+            multiplier,
+        });
+    }
 }
@@ -0,0 +1,86 @@
+use std::cmp::Ordering;
+
+use graph::{Criteria, Graph, LABEL_LIMIT, RoutingError};
+
+/// area reachable from the start within a budget
+pub struct Isochrone {
+    /// distance in km or travel time in h
+    pub budget: f64,
+    /// amount of nodes reachable within the budget
+    pub reachable_nodes: usize,
+    /// highest elevation rise of the cheapest paths to the reachable nodes in m
+    pub max_ele_rise: f64,
+    /// convex hull of the reachable nodes as (longitude, latitude), counterclockwise without repeating the first point
+    pub hull: Vec<(f64, f64)>,
+}
+
+/// returns the area reachable from start within every budget (in km or h), in the order of the budgets
+///
+/// A single one to all search with the largest budget serves all of them. With max_elevation only paths within the
+/// elevation rise count, so hilly areas next to the start may be missing.
+pub fn find_isochrones(graph: &Graph, start: usize, criteria: Criteria, use_distance: bool, budgets: &[f64], max_elevation: Option<f64>) -> Result<Vec<Isochrone>, RoutingError> {
+    if budgets.is_empty() {
+        return Err(RoutingError::InvalidRequest {
+            reason: "An isochrone needs at least one budget".to_string(),
+        });
+    }
+    if budgets.iter().any(|&budget| budget.is_nan() || budget <= 0.0) {
+        return Err(RoutingError::InvalidRequest {
+            reason: "The budgets of isochrones have to be positive".to_string(),
+        });
+    }
+    let max_budget = budgets.iter().cloned().fold(0.0, f64::max);
+    let reachable = graph.find_reachable(start, criteria, use_distance, max_budget, max_elevation, LABEL_LIMIT)?;
+    let isochrones = budgets
+        .iter()
+        .map(|&budget| {
+            let within_budget = reachable.iter().filter(|reachable| reachable.cost <= budget);
+            let max_ele_rise = within_budget.clone().map(|reachable| reachable.ele_rise).fold(0.0, f64::max);
+            let points: Vec<(f64, f64)> = within_budget
+                .map(|reachable| {
+                    let node = graph.get_node(reachable.node);
+                    (node.longitude as f64, node.latitude as f64)
+                })
+                .collect();
+            Isochrone {
+                budget,
+                reachable_nodes: points.len(),
+                max_ele_rise,
+                hull: get_convex_hull(points),
+            }
+        })
+        .collect();
+    return Ok(isochrones);
+}
+
+/// convex hull with Andrew's monotone chain, counterclockwise from the lowest point in (x, y) order
+///
+/// Points on the edges of the hull are dropped, so less than three points are left if all points are on a line.
+pub fn get_convex_hull(mut points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    points.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    // positive if o, a and b are a left turn
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0);
+    let mut hull = Vec::<(f64, f64)>::with_capacity(points.len() + 1);
+    // lower hull from left to right
+    for &point in points.iter() {
+        while hull.len() >= 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(point);
+    }
+    // upper hull back from right to left
+    let lower_length = hull.len() + 1;
+    for &point in points.iter().rev().skip(1) {
+        while hull.len() >= lower_length && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(point);
+    }
+    // the last point is the first one again
+    hull.pop();
+    return hull;
+}
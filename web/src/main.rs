@@ -1,40 +1,44 @@
+// explicit returns are the code style of this project
+#![allow(clippy::needless_return)]
+
 extern crate actix_files;
 extern crate actix_web;
-extern crate bincode;
+extern crate clap;
+extern crate fmi;
 extern crate serde;
 extern crate serde_json;
+extern crate toml;
 extern crate core;
 
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::BufReader;
+use std::iter;
 use std::path::Path;
 use std::time::Instant;
 
 use actix_files as fs;
-use actix_web::{App, HttpServer, middleware, web};
-use bincode::deserialize_from;
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, middleware, web};
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::http::{header, StatusCode};
+use fmi::{map_fmi, MapData, Node, Rider, TravelType};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
-use graph::Graph;
+use config::Config;
+use graph::{Criteria, DijkstraResult, get_leg_ends, Graph, LABEL_LIMIT, RouteStatistics, RoutingError};
+use profile::{load_profiles, Profile};
 
+mod config;
+mod gpx;
 mod graph;
+mod isochrone;
+mod matrix;
+mod profile;
+mod roundtrip;
+#[cfg(test)]
+mod tests;
+mod workspace;
 
-#[derive(Copy, Clone, Deserialize, Debug)]
-pub struct Way {
-    source: usize,
-    target: usize,
-    speed: usize,
-    distance: f32,
-    travel_type: usize,
-}
-
-#[derive(Copy, Clone, Deserialize, Serialize, Debug)]
-pub struct Node {
-    latitude: f32,
-    longitude: f32,
-    elevation: f32,
-}
+/// every via point adds a backward search through the whole graph to the exact search
+const MAX_VIA_POINTS: usize = 25;
 
 #[derive(Copy, Clone, Deserialize, Serialize, Debug)]
 pub struct Position {
@@ -42,141 +46,682 @@ pub struct Position {
     longitude: f32,
 }
 
-
-#[derive(Deserialize, Debug)]
-struct MapData {
-    nodes: Vec<Node>,
-    ways: Vec<Way>,
-    offset: Vec<usize>,
-    grid: HashMap<(usize, usize), Vec<usize>>,
-}
-
 #[derive(Debug, Deserialize, Serialize)]
 struct Query {
     start: Position,
+    /// the route passes these points in order, the elevation limits apply to the whole route
+    #[serde(default)]
+    via: Vec<Position>,
     end: Position,
+    /// car, bicycle or foot, may be left out if the profile is given and has to be its travel type otherwise
+    #[serde(default)]
     travel_type: String,
+    /// name of a profile loaded with --profiles, its speeds and access are used instead of the ones of the travel type
+    profile: Option<String>,
     by_distance: bool,
-    max_ele_rise: i32,
+    /// without limit the shortest path is returned
+    max_ele_rise: Option<i32>,
+    /// limits the total descent like max_ele_rise the total rise, in m
+    max_descent: Option<f64>,
+    /// ways that are steeper uphill or downhill are not used, in percent
+    max_gradient_percent: Option<f64>,
+    /// ways that rise more are not used, in m
+    max_segment_climb: Option<f64>,
+    /// sustained power of bicycle riders in W, their speed on slopes depends on it, not allowed with a profile
+    rider_power: Option<f64>,
+    /// rider and bicycle in kg, not allowed with a profile
+    rider_mass: Option<f64>,
     all_paths: bool,
+    /// "larac" (default) finds paths on the convex hull of distance and elevation, "pareto" all pareto optimal paths
+    /// and "exact" the proven shortest path within max_ele_rise and max_descent
+    algorithm: Option<String>,
+    /// "json", "geojson" for a FeatureCollection with a LineString per route or "gpx" for a GPX file with a track per route,
+    /// the Accept header is used if it is not given
+    format: Option<String>,
+    /// name of the route in GPX files
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct RoundTripQuery {
+    start: Position,
+    /// wanted length of the loops in km
+    length: f64,
+    travel_type: String,
+    max_ele_rise: Option<i32>,
+    /// amount of loops, 3 by default
+    amount: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct IsochroneQuery {
+    start: Position,
+    travel_type: String,
+    by_distance: bool,
+    /// one area per budget, distances in m with by_distance and travel times in s otherwise
+    budgets: Vec<f64>,
+    /// only paths within the elevation rise count, in m
+    max_ele_rise: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct MatrixQuery {
+    sources: Vec<Position>,
+    targets: Vec<Position>,
+    travel_type: String,
+    /// the paths between the points are the shortest ones, otherwise the fastest ones
+    by_distance: bool,
+}
+
+/// rows are the sources and columns the targets, entries are null if there is no path
+#[derive(Debug, Deserialize, Serialize)]
+struct MatrixResponse {
+    distances_m: Vec<Vec<Option<f64>>>,
+    durations_s: Vec<Vec<Option<f64>>>,
+    ascents_m: Vec<Vec<Option<f64>>>,
+}
+
+/// part of a route between two of its waypoints
+#[derive(Debug, Deserialize, Serialize)]
+struct Leg {
+    /// indices of the first and the last node of the leg in the path of the route
+    start_index: usize,
+    end_index: usize,
+    distance_m: f64,
+    duration_s: f64,
+    ascent: f64,
+    descent: f64,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Response {
     path: Vec<Node>,
-    distance: f64,
-    distance_type: String,
+    /// length of the route in m
+    distance_m: f64,
+    /// travel time of the route in s
+    duration_s: f64,
+    /// length and travel time for humans, e.g. "12.35 km, 1 h 05 min"
+    formatted: String,
     elevation: f64,
+    statistics: RouteStatistics,
+    /// one leg from the start to the first via point, between the via points and to the end
+    legs: Vec<Leg>,
+    /// no path within max_ele_rise and max_descent is shorter, in m, only given by distance
+    lower_bound_m: Option<f64>,
+    /// no path within max_ele_rise and max_descent is faster, in s, only given by time
+    lower_bound_s: Option<f64>,
+    /// weight of the elevation in the costs LARAC found the route with
+    multiplier: Option<f64>,
+    /// the optimal route of the response is proven to be the shortest (or fastest) within the limits, false if LARAC could not
+    /// prove it or the exact search exceeded its label limit and answered with the best route of LARAC
+    exact: bool,
+}
+
+/// body of error responses, the code and the details come from the error
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    #[serde(flatten)]
+    error: RoutingError,
+    message: String,
+}
+
+/// searches a route request can use
+#[derive(Debug, PartialEq)]
+enum Algorithm {
+    Larac,
+    Pareto,
+    Exact,
 }
 
-fn query(request: web::Json<Query>, dijkstra: web::Data<Graph>) -> web::Json<Vec<Response>> {
+/// formats of the answer to a route request
+#[derive(Debug, PartialEq)]
+enum Format {
+    Json,
+    GeoJson,
+    Gpx,
+}
+
+fn query(http_request: HttpRequest, request: web::Json<Query>, dijkstra: web::Data<Graph>, profiles: web::Data<Vec<Profile>>) -> HttpResponse {
     let total_time = Instant::now();
     // extract points
     let start: &Position = &request.start;
     let end: &Position = &request.end;
-    let travel_type = match request.travel_type.as_ref() {
-        "car" => 0,
-        "bicycle" => 1,
-        "foot" => 2,
-        _ => 0,
+    let base_criteria = match get_criteria(&request.travel_type, request.profile.as_deref(), &profiles) {
+        Ok(criteria) => criteria,
+        Err(e) => return get_error_response(e),
+    };
+    let rider = match get_rider(request.rider_power, request.rider_mass, request.profile.is_some()) {
+        Ok(rider) => rider,
+        Err(e) => return get_error_response(e),
+    };
+    let accept = http_request.headers().get(header::ACCEPT).and_then(|accept| accept.to_str().ok());
+    let format = match get_format(request.format.as_deref(), accept) {
+        Ok(format) => format,
+        Err(e) => return get_error_response(e),
+    };
+    let algorithm = match get_algorithm(request.algorithm.as_deref()) {
+        Ok(algorithm) => algorithm,
+        Err(e) => return get_error_response(e),
     };
+    let limits = [
+        ("max_ele_rise", request.max_ele_rise.map(|m| m as f64)),
+        ("max_descent", request.max_descent),
+        ("max_gradient_percent", request.max_gradient_percent),
+        ("max_segment_climb", request.max_segment_climb),
+    ];
+    if let Err(e) = check_limits(&limits) {
+        return get_error_response(e);
+    }
     let by_distance: bool = request.by_distance;
     let max_elevation = request.max_ele_rise;
     let all_paths = request.all_paths;
+    let criteria = Criteria {
+        max_gradient_percent: request.max_gradient_percent,
+        max_segment_climb: request.max_segment_climb,
+        rider,
+        ..base_criteria
+    };
     // println!("Start: {},{}", start.latitude, start.longitude);
     // println!("End: {},{}", end.latitude, end.longitude);
     // println!("travel_type: {}, by_distance: {}", travel_type, by_distance);
 
     // search for clicked points
     let timing_find = Instant::now();
-    let start_id: usize = dijkstra.get_point_id(start.latitude, start.longitude, travel_type);
-    let end_id: usize = dijkstra.get_point_id(end.latitude, end.longitude, travel_type);
+    if request.via.len() > MAX_VIA_POINTS {
+        return get_error_response(RoutingError::RequestTooLarge {
+            what: "via points",
+            amount: request.via.len(),
+            limit: MAX_VIA_POINTS,
+        });
+    }
+    let points = iter::once(start).chain(request.via.iter()).chain(iter::once(end));
+    let waypoints = match snap(&dijkstra, points, &criteria) {
+        Ok(waypoints) => waypoints,
+        Err(e) => return get_error_response(e),
+    };
     println!("### duration for get_point_id(): {:?}", timing_find.elapsed());
 
     let timing = Instant::now();
 
-    let tmp = dijkstra.find_optimal_path(start_id, end_id, travel_type, by_distance, max_elevation as f64, all_paths);
+    let max_elevation = max_elevation.map(|m| m as f64);
+    let max_descent = request.max_descent;
+    let tmp = match algorithm {
+        Algorithm::Larac => dijkstra.find_optimal_path(&waypoints, criteria, by_distance, max_elevation, max_descent, all_paths),
+        Algorithm::Pareto => dijkstra.find_pareto_paths(&waypoints, criteria, by_distance, max_elevation, max_descent, all_paths),
+        Algorithm::Exact => dijkstra.find_exact_path(&waypoints, criteria, by_distance, max_elevation, max_descent, all_paths, LABEL_LIMIT),
+    };
     println!("### duration for find_path(): {:?}", timing.elapsed());
+    println!("### search workspace of this thread: {} KiB", graph::workspace_byte_size() / 1024);
 
+    let routes = match tmp {
+        Ok(routes) => routes,
+        Err(e) => return get_error_response(e),
+    };
+    if algorithm == Algorithm::Exact && !routes.exact {
+        println!("### exact search exceeded the limit of {} labels, answered with the best path of LARAC", LABEL_LIMIT);
+    }
     let mut results = Vec::<Response>::new();
-    match tmp {
-        Ok(drs) => {
-            for dr in drs {
-                let result: Vec<Node>;
-                let mut distance: f64;
-                let mut distance_type: String = "".to_string();
-                result = dijkstra.get_nodes(dr.path);
-                match by_distance {
-                    false => {
-                        if dr.distance.trunc() >= 1.0 {
-                            distance = dr.distance;
-                            distance_type.push_str("h ");
-                        }
-                        distance = dr.distance.fract() * 60.0;
-                        distance_type.push_str("min");
-                    }
-                    true => {
-                        distance = dr.distance;
-                        distance_type.push_str("km");
-                    }
-                };
-                results.push(Response{
-                    path: result,
-                    distance,
-                    distance_type,
-                    elevation: dr.ele_rise
-                })
-            }
-        }
-        Err(e) => {
-            println!("{}", e);
-        }
+    for dr in routes.paths {
+        results.push(get_response(&dijkstra, dr, &waypoints, &criteria, by_distance, routes.lower_bound, routes.exact));
     }
 
     println!("result size: {}", results.len());
     println!("### answered request in: {:?}", total_time.elapsed());
-    return web::Json(results);
+    return match format {
+        Format::Json => HttpResponse::Ok().json(results),
+        Format::GeoJson => HttpResponse::Ok().content_type("application/geo+json").json(get_geojson(&results)),
+        Format::Gpx => get_gpx_response(request.name.as_deref().unwrap_or("Route"), &results),
+    };
 }
 
-fn main() {
-    // check if arguments are right
-    let args: Vec<_> = std::env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: {} pbf.fmi_file", args[0]);
-        return;
+/// the algorithm of the request, LARAC if it is not given
+///
+/// Unknown algorithms are rejected instead of answering with LARAC.
+fn get_algorithm(algorithm: Option<&str>) -> Result<Algorithm, RoutingError> {
+    return match algorithm {
+        None | Some("larac") => Ok(Algorithm::Larac),
+        Some("pareto") => Ok(Algorithm::Pareto),
+        Some("exact") => Ok(Algorithm::Exact),
+        Some(algorithm) => Err(RoutingError::InvalidRequest {
+            reason: format!("Unknown algorithm \"{}\", use larac, pareto or exact", algorithm),
+        }),
+    };
+}
+
+/// the format of the request if given, otherwise the first known media type of the Accept header and JSON if there is none
+///
+/// Unknown formats in the request are rejected, unknown media types of the Accept header are skipped.
+fn get_format(format: Option<&str>, accept: Option<&str>) -> Result<Format, RoutingError> {
+    if let Some(format) = format {
+        return match format {
+            "json" => Ok(Format::Json),
+            "geojson" => Ok(Format::GeoJson),
+            "gpx" => Ok(Format::Gpx),
+            _ => Err(RoutingError::InvalidRequest {
+                reason: format!("Unknown format \"{}\", use json, geojson or gpx", format),
+            }),
+        };
+    }
+    for media_type in accept.unwrap_or("").split(',') {
+        // parameters like the quality are ignored
+        match media_type.split(';').next().unwrap_or("").trim() {
+            "application/geo+json" => return Ok(Format::GeoJson),
+            "application/gpx+xml" => return Ok(Format::Gpx),
+            "application/json" => return Ok(Format::Json),
+            _ => {}
+        }
+    }
+    return Ok(Format::Json);
+}
+
+/// GeoJSON FeatureCollection with a LineString of (longitude, latitude, elevation) per route
+fn get_geojson(results: &[Response]) -> Value {
+    let features: Vec<Value> = results
+        .iter()
+        .map(|result| {
+            let coordinates: Vec<[f32; 3]> = result.path.iter().map(|node| [node.longitude, node.latitude, node.elevation]).collect();
+            json!({
+                "type": "Feature",
+                "geometry": { "type": "LineString", "coordinates": coordinates },
+                "properties": {
+                    "distance_m": result.distance_m,
+                    "duration_s": result.duration_s,
+                    "ascent": result.statistics.ascent,
+                    "descent": result.statistics.descent,
+                    "multiplier": result.multiplier,
+                },
+            })
+        })
+        .collect();
+    return json!({ "type": "FeatureCollection", "features": features });
+}
+
+/// GPX file with a track per route as download, the metadata describes the first route
+fn get_gpx_response(name: &str, results: &[Response]) -> HttpResponse {
+    let describe = |result: &Response| format!("{}, {:.0} m ascent", result.formatted, result.statistics.ascent);
+    let tracks: Vec<gpx::Track> = results
+        .iter()
+        .enumerate()
+        .map(|(index, result)| gpx::Track {
+            name: if results.len() == 1 { name.to_string() } else { format!("{} {}", name, index + 1) },
+            description: describe(result),
+            path: &result.path,
+        })
+        .collect();
+    let description = results.first().map(describe).unwrap_or_default();
+    return HttpResponse::Ok()
+        .content_type("application/gpx+xml")
+        .header("Content-Disposition", "attachment; filename=\"route.gpx\"")
+        .body(gpx::write_gpx(name, &description, &tracks));
+}
+
+/// generates loops from the start that return to it, answered like queries for routes
+fn round_trip(request: web::Json<RoundTripQuery>, dijkstra: web::Data<Graph>) -> HttpResponse {
+    let total_time = Instant::now();
+    let travel_type = match get_travel_type(&request.travel_type) {
+        Ok(travel_type) => travel_type,
+        Err(e) => return get_error_response(e),
+    };
+    let criteria = Criteria::new(travel_type);
+    let start = match snap(&dijkstra, iter::once(&request.start), &criteria) {
+        Ok(waypoints) => waypoints[0],
+        Err(e) => return get_error_response(e),
+    };
+    let max_elevation = request.max_ele_rise.map(|m| m as f64);
+    let amount = request.amount.unwrap_or(3);
+
+    let round_trips = match roundtrip::find_round_trips(&dijkstra, start, criteria, request.length, max_elevation, amount) {
+        Ok(round_trips) => round_trips,
+        Err(e) => return get_error_response(e),
+    };
+    let mut results = Vec::<Response>::new();
+    for round_trip in round_trips {
+        results.push(get_response(&dijkstra, round_trip.route, &round_trip.waypoints, &criteria, true, None, false));
     }
+    println!("### answered round trip request in: {:?}", total_time.elapsed());
+    return HttpResponse::Ok().json(results);
+}
+
+/// returns the areas reachable from the start as GeoJSON FeatureCollection with a Polygon per budget
+///
+/// The polygons are the convex hulls of the reachable nodes. If there are less than three nodes that are not on a line, the
+/// feature is a MultiPoint of them instead.
+fn isochrone(request: web::Json<IsochroneQuery>, dijkstra: web::Data<Graph>) -> HttpResponse {
+    let total_time = Instant::now();
+    let travel_type = match get_travel_type(&request.travel_type) {
+        Ok(travel_type) => travel_type,
+        Err(e) => return get_error_response(e),
+    };
+    let criteria = Criteria::new(travel_type);
+    let start = match snap(&dijkstra, iter::once(&request.start), &criteria) {
+        Ok(waypoints) => waypoints[0],
+        Err(e) => return get_error_response(e),
+    };
+    // the searches use km and h
+    let unit = if request.by_distance { 1000.0 } else { 3600.0 };
+    let budgets: Vec<f64> = request.budgets.iter().map(|budget| budget / unit).collect();
+    let max_elevation = request.max_ele_rise.map(|m| m as f64);
+
+    let isochrones = match isochrone::find_isochrones(&dijkstra, start, criteria, request.by_distance, &budgets, max_elevation) {
+        Ok(isochrones) => isochrones,
+        Err(e) => return get_error_response(e),
+    };
+    let mut features = Vec::<Value>::new();
+    for isochrone in isochrones {
+        let mut coordinates: Vec<[f64; 2]> = isochrone.hull.iter().map(|&(longitude, latitude)| [longitude, latitude]).collect();
+        let geometry = if coordinates.len() < 3 {
+            json!({ "type": "MultiPoint", "coordinates": coordinates })
+        } else {
+            // rings of GeoJSON polygons are closed
+            coordinates.push(coordinates[0]);
+            json!({ "type": "Polygon", "coordinates": [coordinates] })
+        };
+        features.push(json!({
+            "type": "Feature",
+            "geometry": geometry,
+            "properties": {
+                "budget": isochrone.budget * unit,
+                "reachable_nodes": isochrone.reachable_nodes,
+                "max_ele_rise": isochrone.max_ele_rise,
+            },
+        }));
+    }
+    println!("### answered isochrone request in: {:?}", total_time.elapsed());
+    return HttpResponse::Ok()
+        .content_type("application/geo+json")
+        .json(json!({ "type": "FeatureCollection", "features": features }));
+}
+
+/// distances, travel times and elevation rises between all sources and targets
+fn matrix(request: web::Json<MatrixQuery>, dijkstra: web::Data<Graph>, config: web::Data<Config>) -> HttpResponse {
+    let total_time = Instant::now();
+    let travel_type = match get_travel_type(&request.travel_type) {
+        Ok(travel_type) => travel_type,
+        Err(e) => return get_error_response(e),
+    };
+    // oversized requests are rejected before their points are snapped
+    if let Err(e) = matrix::check_size(request.sources.len(), request.targets.len(), config.matrix_limit) {
+        return get_error_response(e);
+    }
+    let criteria = Criteria::new(travel_type);
+    let (sources, targets) = match (snap(&dijkstra, request.sources.iter(), &criteria), snap(&dijkstra, request.targets.iter(), &criteria)) {
+        (Ok(sources), Ok(targets)) => (sources, targets),
+        (Err(e), _) | (_, Err(e)) => return get_error_response(e),
+    };
+
+    let scale = |rows: Vec<Vec<Option<f64>>>, factor: f64| -> Vec<Vec<Option<f64>>> {
+        return rows.into_iter().map(|row| row.into_iter().map(|entry| entry.map(|value| value * factor)).collect()).collect();
+    };
+    let matrix = match matrix::find_matrix(&dijkstra, &sources, &targets, criteria, request.by_distance, config.matrix_limit) {
+        Ok(matrix) => matrix,
+        Err(e) => return get_error_response(e),
+    };
+    let response = MatrixResponse {
+        distances_m: scale(matrix.distances, 1000.0),
+        durations_s: scale(matrix.durations, 3600.0),
+        ascents_m: matrix.ascents,
+    };
+    println!("### answered {} x {} matrix request in: {:?}", sources.len(), targets.len(), total_time.elapsed());
+    return HttpResponse::Ok().json(response);
+}
+
+/// snaps the points to the closest nodes with ways the criteria may use
+fn snap<'a, I: Iterator<Item = &'a Position>>(dijkstra: &Graph, points: I, criteria: &Criteria) -> Result<Vec<usize>, RoutingError> {
+    return points
+        .map(|point| {
+            dijkstra.get_point_id(point.latitude, point.longitude, criteria).ok_or(RoutingError::NotSnapped {
+                latitude: point.latitude,
+                longitude: point.longitude,
+            })
+        })
+        .collect();
+}
+
+/// status code of the answer to a request that failed
+fn get_status(error: &RoutingError) -> StatusCode {
+    return match error {
+        RoutingError::InvalidRequest { .. } => StatusCode::BAD_REQUEST,
+        RoutingError::RequestTooLarge { .. } | RoutingError::BodyTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        RoutingError::NoPath | RoutingError::NoPathWithinLimits => StatusCode::NOT_FOUND,
+        RoutingError::NotSnapped { .. } | RoutingError::ElevationInfeasible { .. } | RoutingError::LabelLimitExceeded { .. } => {
+            StatusCode::UNPROCESSABLE_ENTITY
+        }
+    };
+}
+
+/// answers with the status code of the error and its code, details and message as JSON
+fn get_error_response(error: RoutingError) -> HttpResponse {
+    println!("{}", error);
+    return HttpResponse::build(get_status(&error)).json(ErrorResponse {
+        message: error.to_string(),
+        error,
+    });
+}
+
+/// answers json bodies that can not be read like other failed requests instead of with the plain text of actix
+fn get_json_error(error: JsonPayloadError, limit: usize) -> actix_web::Error {
+    let routing_error = match &error {
+        JsonPayloadError::Overflow => RoutingError::BodyTooLarge { limit },
+        error => RoutingError::InvalidRequest {
+            reason: error.to_string(),
+        },
+    };
+    return InternalError::from_response(error, get_error_response(routing_error)).into();
+}
+
+/// travel type of the searches, unknown names are rejected instead of guessing one
+fn get_travel_type(name: &str) -> Result<TravelType, RoutingError> {
+    return TravelType::from_name(name).ok_or_else(|| RoutingError::InvalidRequest {
+        reason: format!("Unknown travel type \"{}\", use car, bicycle or foot", name),
+    });
+}
+
+/// criteria of the profile if the request names one, otherwise of the travel type
+///
+/// A travel type next to a profile has to be the one of the profile instead of being ignored.
+fn get_criteria<'a>(travel_type: &str, profile: Option<&str>, profiles: &'a [Profile]) -> Result<Criteria<'a>, RoutingError> {
+    let name = match profile {
+        Some(name) => name,
+        None => return Ok(Criteria::new(get_travel_type(travel_type)?)),
+    };
+    return match profiles.iter().find(|profile| profile.name == name) {
+        Some(profile) if !travel_type.is_empty() && travel_type != profile.travel_type.name() => Err(RoutingError::InvalidRequest {
+            reason: format!("The profile \"{}\" is for the travel type {}, not {}", name, profile.travel_type.name(), travel_type),
+        }),
+        Some(profile) => Ok(Criteria::with_profile(profile)),
+        None => Err(RoutingError::InvalidRequest {
+            reason: format!("Unknown profile \"{}\"", name),
+        }),
+    };
+}
+
+/// rider of the request, the default rider for the values that are not given,
+/// profiles use their own speed curves so a rider is rejected with them instead of being ignored
+fn get_rider(power: Option<f64>, mass: Option<f64>, with_profile: bool) -> Result<Rider, RoutingError> {
+    if with_profile && (power.is_some() || mass.is_some()) {
+        return Err(RoutingError::InvalidRequest {
+            reason: "rider_power and rider_mass can not be combined with a profile, it uses its own speed curves".to_string(),
+        });
+    }
+    let rider = Rider {
+        power: power.unwrap_or(Rider::DEFAULT.power),
+        mass: mass.unwrap_or(Rider::DEFAULT.mass),
+    };
+    if !rider.power.is_finite() || rider.power <= 0.0 || !rider.mass.is_finite() || rider.mass <= 0.0 {
+        return Err(RoutingError::InvalidRequest {
+            reason: "Power and mass of the rider have to be positive".to_string(),
+        });
+    }
+    return Ok(rider);
+}
+
+/// limits of the request that are given have to be finite and not negative, the searches would fail with misleading errors otherwise
+fn check_limits(limits: &[(&str, Option<f64>)]) -> Result<(), RoutingError> {
+    for &(name, limit) in limits {
+        if let Some(limit) = limit {
+            if !limit.is_finite() || limit < 0.0 {
+                return Err(RoutingError::InvalidRequest {
+                    reason: format!("{} has to be a finite number that is not negative", name),
+                });
+            }
+        }
+    }
+    return Ok(());
+}
+
+/// names and travel types of the profiles a route request can use
+fn list_profiles(profiles: web::Data<Vec<Profile>>) -> HttpResponse {
+    let list: Vec<Value> = profiles.iter().map(|profile| json!({"name": profile.name, "travel_type": profile.travel_type.name()})).collect();
+    return HttpResponse::Ok().json(list);
+}
+
+/// response for a route through the waypoints
+fn get_response(dijkstra: &Graph, dr: DijkstraResult, waypoints: &[usize], criteria: &Criteria, by_distance: bool, lower_bound: Option<f64>, exact: bool) -> Response {
+    let statistics = dijkstra.get_route_statistics(&dr.path, criteria, by_distance);
+    let (length, duration) = dijkstra.get_path_length_and_duration(&dr.path, criteria, by_distance);
+    let distance_m = length * 1000.0;
+    let duration_s = duration * 3600.0;
+    let legs = get_legs(dijkstra, &dr.path, waypoints, criteria, by_distance);
+    let result: Vec<Node> = dijkstra.get_nodes(dr.path);
+    // the lower bound is in the costs of the search
+    let (lower_bound_m, lower_bound_s) = if by_distance {
+        (lower_bound.map(|bound| bound * 1000.0), None)
+    } else {
+        (None, lower_bound.map(|bound| bound * 3600.0))
+    };
+    return Response {
+        path: result,
+        distance_m,
+        duration_s,
+        formatted: format!("{}, {}", format_distance(distance_m), format_duration(duration_s)),
+        elevation: dr.ele_rise,
+        statistics,
+        legs,
+        lower_bound_m,
+        lower_bound_s,
+        multiplier: dr.multiplier,
+        exact,
+    };
+}
+
+/// splits the path of a route at its waypoints
+fn get_legs(dijkstra: &Graph, path: &[usize], waypoints: &[usize], criteria: &Criteria, by_distance: bool) -> Vec<Leg> {
+    let mut legs = Vec::<Leg>::with_capacity(waypoints.len() - 1);
+    let mut start_index = 0;
+    for end_index in get_leg_ends(path, waypoints) {
+        let leg_path = &path[start_index..=end_index];
+        let (length, duration) = dijkstra.get_path_length_and_duration(leg_path, criteria, by_distance);
+        let statistics = dijkstra.get_route_statistics(leg_path, criteria, by_distance);
+        legs.push(Leg {
+            start_index,
+            end_index,
+            distance_m: length * 1000.0,
+            duration_s: duration * 3600.0,
+            ascent: statistics.ascent,
+            descent: statistics.descent,
+        });
+        start_index = end_index;
+    }
+    return legs;
+}
+
+/// formats a distance in m as "850 m" or "12.35 km"
+fn format_distance(distance_m: f64) -> String {
+    return if distance_m < 1000.0 {
+        format!("{:.0} m", distance_m)
+    } else {
+        format!("{:.2} km", distance_m / 1000.0)
+    };
+}
+
+/// formats a duration in s as "7 min" or "1 h 05 min", rounded to full minutes
+fn format_duration(duration_s: f64) -> String {
+    let minutes = (duration_s / 60.0).round() as u64;
+    return if minutes < 60 {
+        format!("{} min", minutes)
+    } else {
+        format!("{} h {:02} min", minutes / 60, minutes % 60)
+    };
+}
+
+/// memory maps an fmi file created by pre, the header is checked before the graph is used
+fn load_map_data(path: &Path) -> Result<MapData, String> {
+    let timing = Instant::now();
+    let (header, map_data) = map_fmi(path)?;
+    let bounding_box = &header.bounding_box;
+    println!(
+        "graph from {} (crc32 {:08x}), created at {} (unix time), covering {},{} to {},{}",
+        header.source_name, header.source_checksum, header.created_at,
+        bounding_box.min_latitude, bounding_box.min_longitude, bounding_box.max_latitude, bounding_box.max_longitude
+    );
+    println!(
+        "mapped {} nodes and {} ways ({} MiB) in {:?}",
+        map_data.nodes.len(), map_data.ways.len(), map_data.byte_size() / (1 << 20), timing.elapsed()
+    );
+    return Ok(map_data);
+}
+
+fn main() {
+    let config = Config::from_args();
 
     // check if file is right
-    let filename = std::env::args_os().nth(1).unwrap();
-    if !Path::new(&filename).exists() {
-        println!("{} not found", filename.into_string().unwrap());
+    if !config.graph_file.exists() {
+        println!("{} not found", config.graph_file.display());
         std::process::exit(1);
     }
 
     // read file
-    let mut f = BufReader::new(File::open(filename).unwrap());
-    let input: MapData = deserialize_from(&mut f).unwrap();
-    let d = Graph::new(input.nodes, input.ways, input.offset, input.grid);
+    let input = match load_map_data(&config.graph_file) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("could not load {}: {}", config.graph_file.display(), e);
+            std::process::exit(1);
+        }
+    };
+    let d = Graph::new(input);
+
+    let profiles = match &config.profiles {
+        Some(path) => match load_profiles(path) {
+            Ok(profiles) => profiles,
+            Err(e) => {
+                eprintln!("could not load profiles: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => Vec::new(),
+    };
+    println!("loaded {} routing profiles", profiles.len());
 
     let graph = web::Data::new(d);
+    let shared_config = web::Data::new(config.clone());
+    let shared_profiles = web::Data::new(profiles);
 
     // check for static-html folder
-    if !Path::new("./static").exists() {
-        eprintln!("./static/ directory not found");
+    if !config.static_folder.exists() {
+        eprintln!("{} directory not found", config.static_folder.display());
         std::process::exit(1);
     }
 
     // start webserver
-    println!("webserver started on http://localhost:8080");
+    let address = format!("{}:{}", config.bind_address, config.port);
+    let static_folder = config.static_folder.clone();
+    let json_limit = config.json_limit;
+    println!("webserver started on http://{}", address);
     HttpServer::new(move || {
         App::new()
             .wrap(middleware::Logger::default())
-            .data(web::JsonConfig::default().limit(1024))
+            .data(web::JsonConfig::default().limit(json_limit).error_handler(move |error, _request| get_json_error(error, json_limit)))
             .register_data(graph.clone())
+            .register_data(shared_config.clone())
+            .register_data(shared_profiles.clone())
             .service(web::resource("/dijkstra").route(web::post().to(query)))
-            .service(fs::Files::new("/", "./static/").index_file("index.html"))
+            .service(web::resource("/roundtrip").route(web::post().to(round_trip)))
+            .service(web::resource("/isochrone").route(web::post().to(isochrone)))
+            .service(web::resource("/matrix").route(web::post().to(matrix)))
+            .service(web::resource("/profiles").route(web::get().to(list_profiles)))
+            .service(fs::Files::new("/", &static_folder).index_file("index.html"))
     })
-        .bind("localhost:8080")
+        .bind(address)
         .unwrap()
         .run()
         .unwrap();
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use graph::{Criteria, Graph, RoutingError};
+
+/// costs between every source and every target, rows are sources and columns targets, None if there is no path
+pub struct Matrix {
+    /// in km
+    pub distances: Vec<Vec<Option<f64>>>,
+    /// in h
+    pub durations: Vec<Vec<Option<f64>>>,
+    /// elevation rise in m
+    pub ascents: Vec<Vec<Option<f64>>>,
+}
+
+/// fails if the matrix is empty or has more than max_cells entries, cheap enough to check before the points are snapped
+pub fn check_size(sources: usize, targets: usize, max_cells: usize) -> Result<(), RoutingError> {
+    if sources == 0 || targets == 0 {
+        return Err(RoutingError::InvalidRequest {
+            reason: "A matrix needs at least one source and one target".to_string(),
+        });
+    }
+    let cells = sources.saturating_mul(targets);
+    if cells > max_cells {
+        return Err(RoutingError::RequestTooLarge {
+            what: "matrix entries",
+            amount: cells,
+            limit: max_cells,
+        });
+    }
+    return Ok(());
+}
+
+/// finds the shortest (or fastest) paths from every source to every target
+///
+/// Every distinct source needs one search that stops once all targets are settled, instead of one search per pair.
+/// Fails like check_size.
+pub fn find_matrix(graph: &Graph, sources: &[usize], targets: &[usize], criteria: Criteria, use_distance: bool, max_cells: usize) -> Result<Matrix, RoutingError> {
+    check_size(sources.len(), targets.len(), max_cells)?;
+    let mut matrix = Matrix {
+        distances: Vec::with_capacity(sources.len()),
+        durations: Vec::with_capacity(sources.len()),
+        ascents: Vec::with_capacity(sources.len()),
+    };
+    // points snapped to the same node share their row
+    let mut rows = HashMap::<usize, usize>::new();
+    for &source in sources {
+        if let Some(&row) = rows.get(&source) {
+            matrix.distances.push(matrix.distances[row].clone());
+            matrix.durations.push(matrix.durations[row].clone());
+            matrix.ascents.push(matrix.ascents[row].clone());
+            continue;
+        }
+        rows.insert(source, matrix.distances.len());
+        let paths = graph.find_one_to_many(source, targets, criteria, use_distance);
+        let mut distances = Vec::with_capacity(targets.len());
+        let mut durations = Vec::with_capacity(targets.len());
+        let mut ascents = Vec::with_capacity(targets.len());
+        for path in paths {
+            match path {
+                Some(path) => {
+                    let (length, duration) = graph.get_path_length_and_duration(&path.path, &criteria, use_distance);
+                    distances.push(Some(length));
+                    durations.push(Some(duration));
+                    ascents.push(Some(path.ele_rise));
+                }
+                None => {
+                    distances.push(None);
+                    durations.push(None);
+                    ascents.push(None);
+                }
+            }
+        }
+        matrix.distances.push(distances);
+        matrix.durations.push(durations);
+        matrix.ascents.push(ascents);
+    }
+    return Ok(matrix);
+}
@@ -0,0 +1,193 @@
+use std::fs;
+use std::path::Path;
+
+use fmi::{RoadClass, Surface, TravelType, Way};
+use serde::Deserialize;
+
+/// speeds in km/h on flat ground, road classes without speed are not used
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoadClassSpeeds {
+    pub car_only: Option<f64>,
+    pub car_and_bicycle: Option<f64>,
+    pub bicycle: Option<f64>,
+    pub bicycle_and_foot: Option<f64>,
+    pub foot: Option<f64>,
+    pub all: Option<f64>,
+}
+
+impl RoadClassSpeeds {
+    pub fn get(&self, road_class: RoadClass) -> Option<f64> {
+        return match road_class {
+            RoadClass::CarOnly => self.car_only,
+            RoadClass::CarAndBicycle => self.car_and_bicycle,
+            RoadClass::Bicycle => self.bicycle,
+            RoadClass::BicycleAndFoot => self.bicycle_and_foot,
+            RoadClass::Foot => self.foot,
+            RoadClass::All => self.all,
+        };
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (RoadClass, f64)> + '_ {
+        return [RoadClass::CarOnly, RoadClass::CarAndBicycle, RoadClass::Bicycle, RoadClass::BicycleAndFoot, RoadClass::Foot, RoadClass::All]
+            .iter()
+            .filter_map(move |&road_class| self.get(road_class).map(|speed| (road_class, speed)));
+    }
+}
+
+/// factors the speed is multiplied with on a surface, 0 excludes ways with the surface
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SurfaceFactors {
+    pub unknown: f64,
+    pub paved: f64,
+    pub cobblestone: f64,
+    pub compacted: f64,
+    pub unpaved: f64,
+}
+
+impl Default for SurfaceFactors {
+    fn default() -> SurfaceFactors {
+        return SurfaceFactors {
+            unknown: 1.0,
+            paved: 1.0,
+            cobblestone: 1.0,
+            compacted: 1.0,
+            unpaved: 1.0,
+        };
+    }
+}
+
+impl SurfaceFactors {
+    pub fn get(&self, surface: Surface) -> f64 {
+        return match surface {
+            Surface::Unknown => self.unknown,
+            Surface::Paved => self.paved,
+            Surface::Cobblestone => self.cobblestone,
+            Surface::Compacted => self.compacted,
+            Surface::Unpaved => self.unpaved,
+        };
+    }
+
+    fn values(&self) -> [f64; 5] {
+        return [self.unknown, self.paved, self.cobblestone, self.compacted, self.unpaved];
+    }
+
+    fn max(&self) -> f64 {
+        return self.values().iter().cloned().fold(0.0, f64::max);
+    }
+}
+
+/// how fast someone travels on which ways, selected by the profile of a request
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub name: String,
+    /// the profile may only use road classes of its travel type, snapping and the travel type of the response go by it
+    pub travel_type: TravelType,
+    pub speeds: RoadClassSpeeds,
+    #[serde(default)]
+    pub surfaces: SurfaceFactors,
+    /// (gradient in percent, speed factor) sorted by gradient, interpolated linearly and constant beyond the last point
+    #[serde(default)]
+    pub uphill: Vec<(f64, f64)>,
+    /// like uphill, with the descent as positive gradient
+    #[serde(default)]
+    pub downhill: Vec<(f64, f64)>,
+}
+
+impl Profile {
+    /// checks if the way has a road class with speed and a surface that is not excluded
+    pub fn may_use(&self, way: &Way) -> bool {
+        let speed = RoadClass::from_u32(way.travel_type).and_then(|road_class| self.speeds.get(road_class));
+        return speed.is_some() && self.surfaces.get(Surface::from_u32(way.surface)) > 0.0;
+    }
+
+    /// travel speed in km/h on a way the profile may use with the gradient in percent, at most the speed limit of the way
+    pub fn get_speed(&self, way: &Way, gradient_percent: f64) -> f64 {
+        let base_speed = RoadClass::from_u32(way.travel_type).and_then(|road_class| self.speeds.get(road_class)).unwrap_or(0.0);
+        let gradient_factor = if gradient_percent >= 0.0 {
+            get_factor(&self.uphill, gradient_percent)
+        } else {
+            get_factor(&self.downhill, -gradient_percent)
+        };
+        let speed = base_speed * self.surfaces.get(Surface::from_u32(way.surface)) * gradient_factor;
+        // ways without speed limit are not capped
+        return if way.speed > 0 {
+            speed.min(way.speed as f64)
+        } else {
+            speed
+        };
+    }
+
+    /// highest speed on any way and gradient in km/h, used for the A* estimate in time mode
+    pub fn get_max_speed(&self) -> f64 {
+        let max_base_speed = self.speeds.iter().map(|(_, speed)| speed).fold(0.0, f64::max);
+        let max_gradient_factor = self.uphill.iter().chain(self.downhill.iter()).map(|&(_, factor)| factor).fold(1.0, f64::max);
+        return max_base_speed * self.surfaces.max() * max_gradient_factor;
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (road_class, speed) in self.speeds.iter() {
+            if !self.travel_type.may_use(road_class) {
+                return Err(format!("profile {}: {} may not use {:?} ways", self.name, self.travel_type.name(), road_class));
+            }
+            if !speed.is_finite() || speed <= 0.0 {
+                return Err(format!("profile {}: speed on {:?} ways has to be positive and finite", self.name, road_class));
+            }
+        }
+        if self.speeds.iter().next().is_none() {
+            return Err(format!("profile {}: needs the speed of at least one road class", self.name));
+        }
+        if self.surfaces.values().iter().any(|&factor| !factor.is_finite() || factor < 0.0) || self.surfaces.max() == 0.0 {
+            return Err(format!("profile {}: surface factors have to be finite and at least one positive", self.name));
+        }
+        for curve in &[&self.uphill, &self.downhill] {
+            if curve.iter().any(|&(gradient, factor)| !gradient.is_finite() || !factor.is_finite() || factor <= 0.0)
+                || curve.windows(2).any(|points| points[0].0 >= points[1].0) {
+                return Err(format!("profile {}: gradients of the speed curves have to increase and their factors be positive", self.name));
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileFile {
+    profile: Vec<Profile>,
+}
+
+/// parses the [[profile]] tables of a toml file, names have to be unique
+pub fn parse_profiles(toml: &str) -> Result<Vec<Profile>, String> {
+    let file: ProfileFile = toml::from_str(toml).map_err(|e| e.to_string())?;
+    for (index, profile) in file.profile.iter().enumerate() {
+        profile.validate()?;
+        if file.profile[..index].iter().any(|other| other.name == profile.name) {
+            return Err(format!("profile {} is defined twice", profile.name));
+        }
+    }
+    return Ok(file.profile);
+}
+
+pub fn load_profiles(path: &Path) -> Result<Vec<Profile>, String> {
+    let toml = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    return parse_profiles(&toml).map_err(|e| format!("{}: {}", path.display(), e));
+}
+
+/// speed factor of the curve at the gradient, 1 without curve
+fn get_factor(curve: &[(f64, f64)], gradient_percent: f64) -> f64 {
+    let (first, last) = match (curve.first(), curve.last()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => return 1.0,
+    };
+    if gradient_percent <= first.0 {
+        return first.1;
+    }
+    if gradient_percent >= last.0 {
+        return last.1;
+    }
+    let index = curve.iter().position(|&(gradient, _)| gradient > gradient_percent).unwrap();
+    let (low, high) = (curve[index - 1], curve[index]);
+    return low.1 + (high.1 - low.1) * (gradient_percent - low.0) / (high.0 - low.0);
+}
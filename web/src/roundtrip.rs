@@ -0,0 +1,183 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use graph::{Criteria, Dijkstra, DijkstraResult, Graph, RoutingError};
+
+/// roads are longer than the straight lines between the corners of a loop
+const DETOUR_FACTOR: f64 = 1.3;
+/// loops whose length is further off are searched again with corners scaled by the error
+const LENGTH_TOLERANCE: f64 = 0.1;
+/// directions the loops are searched in
+pub const DIRECTIONS: usize = 8;
+/// loops that use more of their length twice are dropped, legs avoid the ways of earlier legs so this is rarely needed
+const MAX_REUSED_SHARE: f64 = 0.2;
+/// mean earth radius in km, like calc_distance
+const EARTH_RADIUS: f64 = 6371.0;
+
+/// loop from the start back to it
+pub struct RoundTrip {
+    /// start, the two corners and the start again
+    pub waypoints: Vec<usize>,
+    pub route: DijkstraResult,
+    /// length in km
+    pub length: f64,
+    /// share of the length on ways that are used more than once, in either direction
+    pub reused_share: f64,
+}
+
+/// generates up to amount loops from start with a length of about length km and at most max_elevation m of climbing
+///
+/// Every candidate is a triangle of the start and two corners in one of the DIRECTIONS, routed with LARAC
+/// leg by leg through the corners, every leg avoids the ways of the legs before it. Loops are ranked by the difference to the
+/// wanted length plus the share of their length that is used twice, loops reusing too much are dropped.
+pub fn find_round_trips(graph: &Graph, start: usize, criteria: Criteria, length: f64, max_elevation: Option<f64>, amount: usize) -> Result<Vec<RoundTrip>, RoutingError> {
+    if length <= 0.0 {
+        return Err(RoutingError::InvalidRequest {
+            reason: "The length of a round trip has to be positive".to_string(),
+        });
+    }
+    if amount == 0 {
+        return Err(RoutingError::InvalidRequest {
+            reason: "The amount of round trips has to be positive".to_string(),
+        });
+    }
+    // one loop per direction at most
+    if amount > DIRECTIONS {
+        return Err(RoutingError::RequestTooLarge {
+            what: "round trips",
+            amount,
+            limit: DIRECTIONS,
+        });
+    }
+    let mut round_trips = Vec::<RoundTrip>::new();
+    for direction in 0..DIRECTIONS {
+        let bearing = 360.0 * direction as f64 / DIRECTIONS as f64;
+        // the corners and the start form an equilateral triangle
+        let mut side = length / (3.0 * DETOUR_FACTOR);
+        let mut best: Option<RoundTrip> = None;
+        for _ in 0..2 {
+            let round_trip = match find_round_trip(graph, start, criteria, bearing, side, max_elevation) {
+                Some(round_trip) => round_trip,
+                None => break,
+            };
+            let ratio = round_trip.length / length;
+            if best.as_ref().is_none_or(|best| (best.length - length).abs() > (round_trip.length - length).abs()) {
+                best = Some(round_trip);
+            }
+            if ratio == 0.0 || (ratio - 1.0).abs() <= LENGTH_TOLERANCE {
+                break;
+            }
+            side /= ratio;
+        }
+        round_trips.extend(best);
+    }
+    round_trips.retain(|round_trip| round_trip.length > 0.0 && round_trip.reused_share <= MAX_REUSED_SHARE);
+    let score = |round_trip: &RoundTrip| (round_trip.length - length).abs() / length + round_trip.reused_share;
+    round_trips.sort_by(|a, b| score(a).partial_cmp(&score(b)).unwrap_or(Ordering::Equal));
+    round_trips.dedup_by(|a, b| a.route.path == b.route.path);
+    round_trips.truncate(amount);
+    if round_trips.is_empty() {
+        return Err(RoutingError::NoPathWithinLimits);
+    }
+    return Ok(round_trips);
+}
+
+/// routes the loop through the corners of the triangle in the direction of bearing (in degrees) with sides of side km
+fn find_round_trip(graph: &Graph, start: usize, criteria: Criteria, bearing: f64, side: f64, max_elevation: Option<f64>) -> Option<RoundTrip> {
+    let start_node = graph.get_node(start);
+    // corners without ways nearby, e.g. in a lake, give no loop
+    let corners: Vec<usize> = [bearing - 30.0, bearing + 30.0]
+        .iter()
+        .map(|&corner_bearing| {
+            let (latitude, longitude) = get_destination(start_node.latitude as f64, start_node.longitude as f64, corner_bearing, side);
+            graph.get_point_id(latitude as f32, longitude as f32, &criteria)
+        })
+        .collect::<Option<Vec<usize>>>()?;
+    let waypoints = vec![start, corners[0], corners[1], start];
+    let route = route_avoiding_reuse(graph, &waypoints, criteria, max_elevation)?;
+    let (length, reused_share) = get_reused_share(graph, &route.path, &criteria);
+    return Some(RoundTrip {
+        waypoints,
+        route,
+        length,
+        reused_share,
+    });
+}
+
+/// routes the legs one after another, the ways of earlier legs are avoided so a leg only uses them again if a detour is much longer
+///
+/// The elevation limit is shared by the legs: every leg may climb its lowest possible rise plus an equal part of the budget that the
+/// remaining legs do not need at least, budget a leg does not use is left to the following ones.
+fn route_avoiding_reuse(graph: &Graph, waypoints: &[usize], criteria: Criteria, max_elevation: Option<f64>) -> Option<DijkstraResult> {
+    let legs = waypoints.len() - 1;
+    let min_rises: Vec<f64> = match max_elevation {
+        Some(_) => waypoints
+            .windows(2)
+            .map(|leg| graph.bidirectional_dijkstra(Dijkstra::Elevation, leg[0], leg[1], criteria, true, Some(f64::MAX)).map(|result| result.ele_rise))
+            .collect::<Option<Vec<f64>>>()?,
+        None => vec![0.0; legs],
+    };
+    let mut remaining_elevation = max_elevation;
+    let mut avoided_ways = HashSet::<(usize, usize)>::new();
+    let mut route = DijkstraResult {
+        path: vec![waypoints[0]],
+        distance: 0.0,
+        ele_rise: 0.0,
+        descent: 0.0,
+        multiplier: None,
+    };
+    for (leg, pair) in waypoints.windows(2).enumerate() {
+        if pair[0] == pair[1] {
+            continue;
+        }
+        let leg_limit = match remaining_elevation {
+            Some(remaining_elevation) => {
+                let spare = remaining_elevation - min_rises[leg..].iter().sum::<f64>();
+                if spare < 0.0 {
+                    return None;
+                }
+                Some(min_rises[leg] + spare / (legs - leg) as f64)
+            }
+            None => None,
+        };
+        let avoiding = Criteria {
+            avoided_ways: Some(&avoided_ways),
+            ..criteria
+        };
+        let leg_route = graph.find_optimal_path(pair, avoiding, true, leg_limit, None, false).ok()?.paths.pop()?;
+        avoided_ways.extend(leg_route.path.windows(2).map(|way| (way[0].min(way[1]), way[0].max(way[1]))));
+        remaining_elevation = remaining_elevation.map(|remaining_elevation| remaining_elevation - leg_route.ele_rise);
+        route.ele_rise += leg_route.ele_rise;
+        route.descent += leg_route.descent;
+        route.path.extend_from_slice(&leg_route.path[1..]);
+    }
+    // the costs of the searches contain the penalties of the avoided ways
+    route.distance = graph.get_path_length_and_duration(&route.path, &criteria, true).0;
+    return Some(route);
+}
+
+/// returns the length of the path in km and the share of it on ways that are used more than once
+fn get_reused_share(graph: &Graph, path: &[usize], criteria: &Criteria) -> (f64, f64) {
+    let mut uses = HashMap::<(usize, usize), (usize, f64)>::new();
+    for pair in path.windows(2) {
+        let (length, _) = graph.get_path_length_and_duration(pair, criteria, true);
+        let entry = uses.entry((pair[0].min(pair[1]), pair[0].max(pair[1]))).or_insert((0, length));
+        entry.0 += 1;
+    }
+    let length: f64 = uses.values().map(|&(count, length)| count as f64 * length).sum();
+    let reused: f64 = uses.values().filter(|&&(count, _)| count > 1).map(|&(count, length)| count as f64 * length).sum();
+    if length == 0.0 {
+        return (0.0, 0.0);
+    }
+    return (length, reused / length);
+}
+
+/// returns the point in distance km from the given one in the direction of bearing (in degrees, clockwise from north)
+pub fn get_destination(latitude: f64, longitude: f64, bearing: f64, distance: f64) -> (f64, f64) {
+    let angle = distance / EARTH_RADIUS;
+    let (latitude, longitude, bearing) = (latitude.to_radians(), longitude.to_radians(), bearing.to_radians());
+    let destination_latitude = (latitude.sin() * angle.cos() + latitude.cos() * angle.sin() * bearing.cos()).asin();
+    let destination_longitude = longitude
+        + (bearing.sin() * angle.sin() * latitude.cos()).atan2(angle.cos() - latitude.sin() * destination_latitude.sin());
+    return (destination_latitude.to_degrees(), destination_longitude.to_degrees());
+}
@@ -0,0 +1,995 @@
+use super::*;
+use std::collections::HashSet;
+use fmi::{calc_distance, ContractionHierarchy, get_walking_speed, Surface, Way};
+
+use graph::{Criteria, Dijkstra, DijkstraResult, LABEL_LIMIT, RoutingError};
+
+/// builds a ring graph where every node has an edge of the given travel type to its successor
+fn build_graph(coordinates: &[(f32, f32)], travel_type: u32) -> Graph {
+    let nodes: Vec<Node> = coordinates
+        .iter()
+        .map(|&(latitude, longitude)| Node {
+            latitude,
+            longitude,
+            elevation: 0.0,
+        })
+        .collect();
+    let mut ways = Vec::<Way>::new();
+    for i in 0..nodes.len() {
+        let target = (i + 1) % nodes.len();
+        ways.push(Way {
+            source: i as u32,
+            target: target as u32,
+            speed: 30,
+            distance: 1.0,
+            travel_type,
+            surface: 0,
+        });
+    }
+    let map_data = MapData::new(nodes, ways);
+    return Graph::new(map_data);
+}
+
+#[test]
+fn snap_across_prime_meridian_test() {
+    // two nodes in greenwich, one on each side of the meridian
+    let graph = build_graph(&[(51.4779, -0.0015), (51.4779, 0.0015)], 5);
+    assert_eq!(graph.get_point_id(51.4779, -0.0005, &Criteria::new(TravelType::Bicycle)), Some(0));
+    assert_eq!(graph.get_point_id(51.4779, 0.0005, &Criteria::new(TravelType::Bicycle)), Some(1));
+    // far west of the nodes, search has to expand to the neighbor cells
+    assert_eq!(graph.get_point_id(51.4779, -0.05, &Criteria::new(TravelType::Bicycle)), Some(0));
+    assert_eq!(graph.get_point_id(51.4779, 0.05, &Criteria::new(TravelType::Bicycle)), Some(1));
+}
+
+#[test]
+fn snap_across_equator_test() {
+    // two nodes near quito, one on each side of the equator
+    let graph = build_graph(&[(0.0015, -78.4550), (-0.0015, -78.4550)], 5);
+    assert_eq!(graph.get_point_id(0.0005, -78.4550, &Criteria::new(TravelType::Car)), Some(0));
+    assert_eq!(graph.get_point_id(-0.0005, -78.4550, &Criteria::new(TravelType::Car)), Some(1));
+    assert_eq!(graph.get_point_id(0.05, -78.4550, &Criteria::new(TravelType::Car)), Some(0));
+    assert_eq!(graph.get_point_id(-0.05, -78.4550, &Criteria::new(TravelType::Car)), Some(1));
+}
+
+#[test]
+fn snap_southern_and_western_hemisphere_test() {
+    // buenos aires and madrid
+    let graph = build_graph(
+        &[(-34.6037, -58.3816), (-34.6090, -58.3900), (40.4168, -3.7038)],
+        5,
+    );
+    assert_eq!(graph.get_point_id(-34.6040, -58.3820, &Criteria::new(TravelType::Foot)), Some(0));
+    assert_eq!(graph.get_point_id(-34.6085, -58.3890, &Criteria::new(TravelType::Foot)), Some(1));
+    assert_eq!(graph.get_point_id(40.4100, -3.7100, &Criteria::new(TravelType::Foot)), Some(2));
+}
+
+/// builds a size x size grid graph around stuttgart with hilly elevations, main roads and some footways
+fn build_grid_map_data(size: usize) -> MapData {
+    let mut nodes = Vec::<Node>::with_capacity(size * size);
+    for row in 0..size {
+        for column in 0..size {
+            nodes.push(Node {
+                latitude: 48.7 + row as f32 * 0.001,
+                longitude: 9.1 + column as f32 * 0.0015,
+                elevation: 250.0 + 40.0 * ((row as f32 * 0.3).sin() + (column as f32 * 0.17).cos()),
+            });
+        }
+    }
+    let mut ways = Vec::<Way>::new();
+    for row in 0..size {
+        for column in 0..size {
+            let id = row * size + column;
+            let mut neighbors = Vec::new();
+            if column + 1 < size {
+                neighbors.push(id + 1);
+            }
+            if row + 1 < size {
+                neighbors.push(id + size);
+            }
+            for neighbor in neighbors {
+                let distance = calc_distance(nodes[id].latitude, nodes[id].longitude, nodes[neighbor].latitude, nodes[neighbor].longitude);
+                // like a city, every 10th street is an arterial road and every 5th one a main street
+                let street = if neighbor == id + 1 { row } else { column };
+                let speed = match street {
+                    street if street % 10 == 0 => 100,
+                    street if street % 5 == 0 => 50,
+                    _ => 30,
+                };
+                let travel_type = if speed == 30 && (row * 5 + column * 11 + neighbor) % 6 == 0 { 4 } else { 5 };
+                for &(source, target) in &[(id, neighbor), (neighbor, id)] {
+                    ways.push(Way {
+                        source: source as u32,
+                        target: target as u32,
+                        speed,
+                        distance,
+                        travel_type,
+                        surface: 0,
+                    });
+                }
+            }
+        }
+    }
+    return MapData::new(nodes, ways);
+}
+
+fn build_grid_graph(size: usize) -> Graph {
+    return Graph::new(build_grid_map_data(size));
+}
+
+/// compares the optimized costs, equally expensive paths may differ in the other value
+fn assert_same_costs(a: Option<DijkstraResult>, b: Option<DijkstraResult>, multiplier: Option<f64>) {
+    let (a, b) = match (a, b) {
+        (Some(a), Some(b)) => (a, b),
+        (None, None) => return,
+        _ => panic!("only one search found a path"),
+    };
+    let cost = |result: &DijkstraResult| match multiplier {
+        Some(multiplier) => result.distance + multiplier * result.ele_rise,
+        None => result.ele_rise,
+    };
+    assert!((cost(&a) - cost(&b)).abs() < 1e-6 * cost(&b).max(1.0), "cost {} != {}", cost(&a), cost(&b));
+}
+
+#[test]
+fn a_star_matches_dijkstra_test() {
+    let graph = build_grid_graph(30);
+    let queries = [(0, 899), (899, 0), (15, 884), (437, 12), (5, 5)];
+    for &(start, end) in queries.iter() {
+        for &travel_type in TravelType::ALL.iter() {
+            for &use_distance in &[true, false] {
+                assert_same_costs(
+                    graph.a_star(Dijkstra::Elevation, start, end, Criteria::new(travel_type), use_distance, Some(f64::MAX)),
+                    graph.dijkstra(Dijkstra::Elevation, start, end, Criteria::new(travel_type), use_distance, Some(f64::MAX)),
+                    None,
+                );
+                for &multiplier in &[0.0, 0.01, 0.5, 10.0] {
+                    assert_same_costs(
+                        graph.a_star(Dijkstra::Multiplier, start, end, Criteria::new(travel_type), use_distance, Some(multiplier)),
+                        graph.dijkstra(Dijkstra::Multiplier, start, end, Criteria::new(travel_type), use_distance, Some(multiplier)),
+                        Some(multiplier),
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn bidirectional_dijkstra_matches_dijkstra_test() {
+    let graph = build_grid_graph(30);
+    let queries = [(0, 899), (899, 0), (15, 884), (437, 12), (5, 5)];
+    for &(start, end) in queries.iter() {
+        for &travel_type in TravelType::ALL.iter() {
+            for &use_distance in &[true, false] {
+                let result = graph.bidirectional_dijkstra(Dijkstra::Elevation, start, end, Criteria::new(travel_type), use_distance, Some(f64::MAX));
+                if let Some(result) = &result {
+                    assert_eq!((result.path[0], *result.path.last().unwrap()), (start, end));
+                }
+                assert_same_costs(
+                    result,
+                    graph.dijkstra(Dijkstra::Elevation, start, end, Criteria::new(travel_type), use_distance, Some(f64::MAX)),
+                    None,
+                );
+                for &multiplier in &[0.0, 0.01, 0.5, 10.0] {
+                    assert_same_costs(
+                        graph.bidirectional_dijkstra(Dijkstra::Multiplier, start, end, Criteria::new(travel_type), use_distance, Some(multiplier)),
+                        graph.dijkstra(Dijkstra::Multiplier, start, end, Criteria::new(travel_type), use_distance, Some(multiplier)),
+                        Some(multiplier),
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn bidirectional_dijkstra_one_way_test() {
+    // ring graph, every way can only be used in one direction
+    let graph = build_graph(&[(48.0, 9.0), (48.001, 9.0), (48.001, 9.001), (48.0, 9.001)], 5);
+    let result = graph.bidirectional_dijkstra(Dijkstra::Multiplier, 0, 2, Criteria::new(TravelType::Bicycle), true, Some(0.0)).unwrap();
+    assert_eq!(result.path, vec![0, 1, 2]);
+    assert_eq!(result.distance, 2.0);
+    let result = graph.bidirectional_dijkstra(Dijkstra::Multiplier, 2, 1, Criteria::new(TravelType::Bicycle), true, Some(0.0)).unwrap();
+    assert_eq!(result.path, vec![2, 3, 0, 1]);
+    assert_eq!(result.distance, 3.0);
+    // foot is not allowed on the ways of type 0
+    let car_only = build_graph(&[(48.0, 9.0), (48.001, 9.0)], 0);
+    assert!(car_only.bidirectional_dijkstra(Dijkstra::Multiplier, 0, 1, Criteria::new(TravelType::Foot), true, Some(0.0)).is_none());
+}
+
+#[test]
+fn contraction_hierarchy_matches_dijkstra_test() {
+    let mut map_data = build_grid_map_data(20);
+    for &travel_type in TravelType::ALL.iter() {
+        for &by_distance in &[true, false] {
+            let ch = ContractionHierarchy::new(&map_data, travel_type, by_distance);
+            map_data.hierarchies.push(ch);
+        }
+    }
+    let graph = Graph::new(map_data);
+    let queries = [(0, 399), (399, 0), (15, 384), (217, 12), (5, 5), (3, 4)];
+    for &(start, end) in queries.iter() {
+        for &travel_type in TravelType::ALL.iter() {
+            for &use_distance in &[true, false] {
+                let result = graph.shortest_path(start, end, Criteria::new(travel_type), use_distance);
+                if let Some(result) = &result {
+                    assert_eq!((result.path[0], *result.path.last().unwrap()), (start, end));
+                }
+                assert_same_costs(
+                    result,
+                    graph.dijkstra(Dijkstra::Multiplier, start, end, Criteria::new(travel_type), use_distance, Some(0.0)),
+                    Some(0.0),
+                );
+            }
+        }
+    }
+    // without elevation limit only the shortest path is returned
+    let routes = graph.find_optimal_path(&[0, 399], Criteria::new(TravelType::Foot), true, None, None, true).unwrap();
+    assert_eq!(routes.paths.len(), 1);
+    assert!(routes.lower_bound.is_none());
+}
+
+#[test]
+fn workspace_labels_test() {
+    let mut labels = workspace::Labels::new();
+    labels.reset(3);
+    labels.set(1, 2.0, Some(0));
+    assert_eq!(labels.get(1), (2.0, Some(0)));
+    assert_eq!(labels.cost(2), f64::MAX);
+    labels.reset(5);
+    assert_eq!(labels.get(1), (f64::MAX, None));
+    labels.set(4, 1.0, None);
+    assert_eq!(labels.cost(4), 1.0);
+
+    // the labels of a route with many via points are freed after its search
+    let mut workspace = workspace::SearchWorkspace::<u32>::new();
+    workspace.add_leg_labels(50, 100);
+    assert_eq!(workspace.leg_labels.len(), 50);
+    workspace.release_leg_labels();
+    assert_eq!(workspace.leg_labels.len(), workspace::KEPT_LEG_LABELS);
+    assert!(workspace.byte_size() <= workspace::KEPT_LEG_LABELS * workspace.leg_labels[0].byte_size());
+}
+
+#[test]
+fn workspace_reuse_test() {
+    // the searches share the workspace of this thread, results must not depend on the previous search
+    let small = build_grid_graph(5);
+    let graph = build_grid_graph(30);
+    let first = graph.find_optimal_path(&[0, 899], Criteria::new(TravelType::Bicycle), true, Some(200.0), None, true).unwrap().paths;
+    small.a_star(Dijkstra::Multiplier, 0, 24, Criteria::new(TravelType::Bicycle), true, Some(0.0)).unwrap();
+    graph.bidirectional_dijkstra(Dijkstra::Elevation, 899, 0, Criteria::new(TravelType::Bicycle), false, Some(f64::MAX)).unwrap();
+    let second = graph.find_optimal_path(&[0, 899], Criteria::new(TravelType::Bicycle), true, Some(200.0), None, true).unwrap().paths;
+    assert!(first.len() >= 2);
+    assert_eq!(first.len(), second.len());
+    for (a, b) in first.iter().zip(second.iter()) {
+        assert_eq!(a.path, b.path);
+        assert_eq!((a.distance, a.ele_rise), (b.distance, b.ele_rise));
+    }
+    assert!(graph::workspace_byte_size() >= 2 * 900 * 4);
+}
+
+/// three routes from node 0 to node 4 over the nodes 1, 2 and 3 with (distance, elevation rise) of (1, 10), (6, 6) and (10, 1)
+fn build_detour_graph() -> Graph {
+    let elevations = [0.0, 10.0, 6.0, 1.0, 0.0];
+    let nodes: Vec<Node> = elevations
+        .iter()
+        .enumerate()
+        .map(|(i, &elevation)| Node {
+            latitude: 48.0 + i as f32 * 0.00001,
+            longitude: 9.0,
+            elevation,
+        })
+        .collect();
+    let mut ways = Vec::<Way>::new();
+    for &(middle, distance) in [(1, 0.5), (2, 3.0), (3, 5.0)].iter() {
+        for &(source, target) in [(0, middle), (middle, 4)].iter() {
+            ways.push(Way {
+                source,
+                target,
+                speed: 30,
+                distance,
+                travel_type: 5,
+                surface: 0,
+            });
+        }
+    }
+    return Graph::new(MapData::new(nodes, ways));
+}
+
+#[test]
+fn pareto_finds_unsupported_paths_test() {
+    let graph = build_detour_graph();
+    let frontier = graph.pareto_search(&[0, 4], Criteria::new(TravelType::Bicycle), true, (None, None), LABEL_LIMIT).unwrap();
+    let costs: Vec<(f64, f64)> = frontier.iter().map(|result| (result.distance, result.ele_rise)).collect();
+    assert_eq!(costs, vec![(1.0, 10.0), (6.0, 6.0), (10.0, 1.0)]);
+    assert_eq!(frontier[1].path, vec![0, 2, 4]);
+    // (6, 6) is above the line between the other two paths, LARAC can not find it
+    let larac = graph.find_optimal_path(&[0, 4], Criteria::new(TravelType::Bicycle), true, Some(8.0), None, true).unwrap();
+    assert!(larac.paths.iter().all(|result| result.path != vec![0, 2, 4]));
+    let best = graph.find_pareto_paths(&[0, 4], Criteria::new(TravelType::Bicycle), true, Some(8.0), None, false).unwrap();
+    assert_eq!(best.paths[0].path, vec![0, 2, 4]);
+    assert_eq!(best.lower_bound, Some(6.0));
+    assert!(graph.find_pareto_paths(&[0, 4], Criteria::new(TravelType::Bicycle), true, Some(0.5), None, true).is_err());
+}
+
+#[test]
+fn pareto_matches_dijkstra_test() {
+    let graph = build_grid_graph(12);
+    let queries = [(0, 143), (143, 0), (6, 137), (5, 5)];
+    for &(start, end) in queries.iter() {
+        for &travel_type in TravelType::ALL.iter() {
+            for &use_distance in &[true, false] {
+                let frontier = graph.pareto_search(&[start, end], Criteria::new(travel_type), use_distance, (None, None), LABEL_LIMIT).unwrap();
+                for pair in frontier.windows(2) {
+                    assert!(pair[0].distance < pair[1].distance && pair[0].ele_rise > pair[1].ele_rise);
+                }
+                // every optimum of a weighted sum is a pareto optimal path
+                for &multiplier in &[0.0, 0.01, 0.5, 10.0] {
+                    let best = frontier
+                        .iter()
+                        .min_by(|a, b| (a.distance + multiplier * a.ele_rise).partial_cmp(&(b.distance + multiplier * b.ele_rise)).unwrap())
+                        .cloned();
+                    assert_same_costs(
+                        best,
+                        graph.dijkstra(Dijkstra::Multiplier, start, end, Criteria::new(travel_type), use_distance, Some(multiplier)),
+                        Some(multiplier),
+                    );
+                }
+                assert_same_costs(
+                    frontier.last().cloned(),
+                    graph.dijkstra(Dijkstra::Elevation, start, end, Criteria::new(travel_type), use_distance, Some(f64::MAX)),
+                    None,
+                );
+                for result in frontier.iter() {
+                    assert_eq!((result.path[0], *result.path.last().unwrap()), (start, end));
+                }
+            }
+        }
+    }
+    assert!(graph.pareto_search(&[0, 143], Criteria::new(TravelType::Bicycle), true, (None, None), 10).is_err());
+}
+
+#[test]
+fn exact_path_test() {
+    let graph = build_detour_graph();
+    // LARAC stops at (10, 1) with a lagrangian lower bound of 11 - 8 = 3
+    let larac = graph.find_optimal_path(&[0, 4], Criteria::new(TravelType::Bicycle), true, Some(8.0), None, false).unwrap();
+    assert_eq!(larac.paths[0].path, vec![0, 3, 4]);
+    assert_eq!(larac.lower_bound, Some(3.0));
+    let exact = graph.find_exact_path(&[0, 4], Criteria::new(TravelType::Bicycle), true, Some(8.0), None, false, LABEL_LIMIT).unwrap();
+    assert_eq!(exact.paths.len(), 1);
+    assert_eq!(exact.paths[0].path, vec![0, 2, 4]);
+    assert_eq!(exact.lower_bound, Some(6.0));
+    assert!(exact.exact && !larac.exact);
+    // with recommendations the optimal path is the last one
+    let exact = graph.find_exact_path(&[0, 4], Criteria::new(TravelType::Bicycle), true, Some(8.0), None, true, LABEL_LIMIT).unwrap();
+    assert_eq!(exact.paths.last().unwrap().path, vec![0, 2, 4]);
+    // if the search gives up, the path of LARAC is returned together with its lower bound
+    let fallback = graph.find_exact_path(&[0, 4], Criteria::new(TravelType::Bicycle), true, Some(8.0), None, false, 1).unwrap();
+    assert_eq!(fallback.paths[0].path, vec![0, 3, 4]);
+    assert_eq!(fallback.lower_bound, Some(3.0));
+    assert!(!fallback.exact);
+    assert!(graph.find_exact_path(&[0, 4], Criteria::new(TravelType::Bicycle), true, Some(0.5), None, false, LABEL_LIMIT).is_err());
+}
+
+#[test]
+fn exact_path_matches_pareto_test() {
+    let graph = build_grid_graph(12);
+    let queries = [(0, 143), (143, 0), (6, 137), (130, 20)];
+    for &(start, end) in queries.iter() {
+        for &travel_type in TravelType::ALL.iter() {
+            for &use_distance in &[true, false] {
+                let frontier = graph.pareto_search(&[start, end], Criteria::new(travel_type), use_distance, (None, None), LABEL_LIMIT).unwrap();
+                if frontier.is_empty() {
+                    continue;
+                }
+                let limits = frontier.iter().flat_map(|result| vec![result.ele_rise, result.ele_rise + 0.5]);
+                for max_elevation in limits {
+                    // the shortest pareto optimal path within the limit is the optimum
+                    let optimum = frontier.iter().find(|result| result.ele_rise <= max_elevation).unwrap();
+                    let exact = graph.find_exact_path(&[start, end], Criteria::new(travel_type), use_distance, Some(max_elevation), None, false, LABEL_LIMIT).unwrap();
+                    let path = &exact.paths[0];
+                    assert!(path.ele_rise <= max_elevation);
+                    assert!((path.distance - optimum.distance).abs() < 1e-9, "{} != {}", path.distance, optimum.distance);
+                    let lower_bound = exact.lower_bound.unwrap();
+                    assert!((lower_bound - optimum.distance).abs() < 1e-9);
+                    let larac = graph.find_optimal_path(&[start, end], Criteria::new(travel_type), use_distance, Some(max_elevation), None, false).unwrap();
+                    assert!(larac.lower_bound.unwrap() <= optimum.distance + 1e-9);
+                }
+            }
+        }
+    }
+}
+
+/// the grid graph with bumps, the elevations of the plain grid add up to the same rise and descent on many paths
+fn build_bumpy_grid_graph(size: usize) -> Graph {
+    let map_data = build_grid_map_data(size);
+    let nodes: Vec<Node> = map_data.nodes
+        .iter()
+        .enumerate()
+        .map(|(id, node)| Node {
+            elevation: node.elevation + 15.0 * ((id * 7919 % 13) as f32 / 13.0),
+            ..*node
+        })
+        .collect();
+    return Graph::new(MapData::new(nodes, map_data.ways.to_vec()));
+}
+
+#[test]
+fn descent_limit_matches_pareto_test() {
+    let graph = build_bumpy_grid_graph(12);
+    let queries = [(0, 143), (143, 0), (6, 137), (130, 20)];
+    for &(start, end) in queries.iter() {
+        for &use_distance in &[true, false] {
+            let shortest = graph.shortest_path(start, end, Criteria::new(TravelType::Bicycle), use_distance).unwrap();
+            for &rise_factor in &[None, Some(0.5), Some(1.0)] {
+                for &descent_factor in &[0.3, 0.6, 0.9] {
+                    let max_elevation = rise_factor.map(|factor| factor * shortest.ele_rise);
+                    let max_descent = descent_factor * shortest.descent;
+                    let limits = (max_elevation, Some(max_descent));
+                    // the shortest path of the frontier within both limits is the optimum
+                    let optimum = graph.pareto_search(&[start, end], Criteria::new(TravelType::Bicycle), use_distance, limits, LABEL_LIMIT).unwrap();
+                    let exact = graph.find_exact_path(&[start, end], Criteria::new(TravelType::Bicycle), use_distance, max_elevation, Some(max_descent), false, LABEL_LIMIT);
+                    let larac = graph.find_optimal_path(&[start, end], Criteria::new(TravelType::Bicycle), use_distance, max_elevation, Some(max_descent), true);
+                    let optimum = match optimum.first() {
+                        Some(optimum) => optimum,
+                        None => {
+                            assert!(exact.is_err() && larac.is_err());
+                            continue;
+                        }
+                    };
+                    assert!(optimum.descent <= max_descent);
+                    let exact = exact.unwrap();
+                    let path = &exact.paths[0];
+                    assert!(path.descent <= max_descent && path.ele_rise <= max_elevation.unwrap_or(f64::MAX));
+                    assert!((path.distance - optimum.distance).abs() < 1e-9, "{} != {}", path.distance, optimum.distance);
+                    let larac = larac.unwrap();
+                    for result in larac.paths.iter() {
+                        assert!(result.descent <= max_descent && result.ele_rise <= max_elevation.unwrap_or(f64::MAX));
+                    }
+                    assert!(larac.lower_bound.unwrap() <= optimum.distance + 1e-9);
+                    assert!(larac.paths.last().unwrap().distance >= optimum.distance - 1e-9);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn steep_ways_test() {
+    let graph = build_detour_graph();
+    let criteria = Criteria::new(TravelType::Bicycle);
+    // the ways over node 1 have a gradient of 2 %, the ones over node 2 of 0.2 %
+    let gentle = Criteria {
+        max_gradient_percent: Some(1.0),
+        ..criteria
+    };
+    let routes = graph.find_optimal_path(&[0, 4], gentle, true, None, None, false).unwrap();
+    assert_eq!(routes.paths[0].path, vec![0, 2, 4]);
+    let low_climbs = Criteria {
+        max_segment_climb: Some(2.0),
+        ..criteria
+    };
+    let routes = graph.find_exact_path(&[0, 4], low_climbs, true, Some(5.0), None, false, LABEL_LIMIT).unwrap();
+    assert_eq!(routes.paths[0].path, vec![0, 3, 4]);
+    let flat = Criteria {
+        max_gradient_percent: Some(0.01),
+        ..criteria
+    };
+    assert!(graph.find_pareto_paths(&[0, 4], flat, true, None, None, true).is_err());
+}
+
+#[test]
+fn route_statistics_test() {
+    let graph = build_detour_graph();
+    let criteria = Criteria::new(TravelType::Bicycle);
+    let statistics = graph.get_route_statistics(&[0, 1, 4], &criteria, true);
+    assert_eq!((statistics.ascent, statistics.descent), (10.0, 10.0));
+    assert_eq!((statistics.min_elevation, statistics.max_elevation), (0.0, 10.0));
+    assert!((statistics.max_gradient_percent - 2.0).abs() < 1e-6);
+    assert!((statistics.average_gradient_percent - 2.0).abs() < 1e-6);
+    assert_eq!(statistics.max_segment_climb, 10.0);
+    let profile: Vec<(f64, f64)> = statistics.profile.iter().map(|point| (point.distance, point.elevation)).collect();
+    assert_eq!(profile, vec![(0.0, 0.0), (0.5, 10.0), (1.0, 0.0)]);
+    let statistics = graph.get_route_statistics(&[0, 2, 4], &criteria, true);
+    assert_eq!((statistics.ascent, statistics.descent, statistics.max_elevation), (6.0, 6.0, 6.0));
+    assert!((statistics.max_gradient_percent - 0.2).abs() < 1e-6);
+    assert!((statistics.average_gradient_percent - 0.2).abs() < 1e-6);
+    assert_eq!(statistics.profile.last().unwrap().distance, 6.0);
+    // a route of a single node has no length
+    let statistics = graph.get_route_statistics(&[2], &criteria, true);
+    assert_eq!((statistics.min_elevation, statistics.max_elevation, statistics.average_gradient_percent), (6.0, 6.0, 0.0));
+    assert_eq!(statistics.profile.len(), 1);
+}
+
+#[test]
+fn length_and_duration_test() {
+    let graph = build_detour_graph();
+    // 0.5 km per way, bicycles climb 10 m slowly and roll down faster than the speed limit of 30 km/h allows
+    let (length, duration) = graph.get_path_length_and_duration(&[0, 1, 4], &Criteria::new(TravelType::Bicycle), false);
+    assert_eq!(length, 1.0);
+    assert!((duration - (0.5 / Rider::DEFAULT.get_speed(2.0) + 0.5 / 30.0)).abs() < 1e-9);
+    let strong = Criteria {
+        rider: Rider {
+            power: 250.0,
+            mass: 80.0,
+        },
+        ..Criteria::new(TravelType::Bicycle)
+    };
+    let (_, strong_duration) = graph.get_path_length_and_duration(&[0, 1, 4], &strong, false);
+    assert!(strong_duration < duration);
+    let (_, duration) = graph.get_path_length_and_duration(&[0, 1, 4], &Criteria::new(TravelType::Foot), false);
+    assert!((duration - (0.5 / get_walking_speed(2.0) + 0.5 / get_walking_speed(-2.0))).abs() < 1e-9);
+    let (length, duration) = graph.get_path_length_and_duration(&[0, 3, 4], &Criteria::new(TravelType::Car), true);
+    assert_eq!(length, 10.0);
+    assert!((duration - 1.0 / 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn format_test() {
+    assert_eq!(format_distance(850.4), "850 m");
+    assert_eq!(format_distance(12345.0), "12.35 km");
+    assert_eq!(format_duration(400.0), "7 min");
+    assert_eq!(format_duration(3600.0), "1 h 00 min");
+    // the hours are kept, 1.55 h are 1 h 33 min
+    assert_eq!(format_duration(1.55 * 3600.0), "1 h 33 min");
+    assert_eq!(format_duration(3599.0), "1 h 00 min");
+}
+
+#[test]
+fn algorithm_test() {
+    assert_eq!(get_algorithm(None), Ok(Algorithm::Larac));
+    assert_eq!(get_algorithm(Some("larac")), Ok(Algorithm::Larac));
+    assert_eq!(get_algorithm(Some("pareto")), Ok(Algorithm::Pareto));
+    assert_eq!(get_algorithm(Some("exact")), Ok(Algorithm::Exact));
+    // typos are not answered with LARAC
+    let error = get_algorithm(Some("pareot")).unwrap_err();
+    assert_eq!(serde_json::to_value(&error).unwrap()["code"], "invalid_request");
+    assert_eq!(get_status(&error), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn via_points_test() {
+    let graph = build_bumpy_grid_graph(12);
+    let criteria = Criteria::new(TravelType::Bicycle);
+    for waypoints in [vec![0, 77, 143], vec![130, 20, 140, 6], vec![5, 5, 20, 20]].iter() {
+        let legs: Vec<DijkstraResult> = waypoints
+            .windows(2)
+            .map(|leg| graph.dijkstra(Dijkstra::Multiplier, leg[0], leg[1], criteria, true, Some(0.0)).unwrap())
+            .collect();
+        let shortest = graph.find_optimal_path(waypoints, criteria, true, None, None, false).unwrap();
+        let route = &shortest.paths[0];
+        assert!((route.distance - legs.iter().map(|leg| leg.distance).sum::<f64>()).abs() < 1e-9);
+        // the legs are joined without duplicate nodes
+        assert!(route.path.windows(2).all(|pair| pair[0] != pair[1]));
+        let ends = graph::get_leg_ends(&route.path, waypoints);
+        assert_eq!(ends.len(), waypoints.len() - 1);
+        for (end, waypoint) in ends.iter().zip(waypoints.iter().skip(1)) {
+            assert_eq!(route.path[*end], *waypoint);
+        }
+        assert_eq!(*ends.last().unwrap(), route.path.len() - 1);
+
+        // the elevation limit is shared by all legs, the shortest path of the frontier of the whole route within it is the optimum
+        let frontier = graph.pareto_search(waypoints, criteria, true, (None, None), LABEL_LIMIT).unwrap();
+        assert!((frontier[0].distance - route.distance).abs() < 1e-9);
+        for pair in frontier.windows(2) {
+            assert!(pair[0].distance < pair[1].distance && pair[0].ele_rise > pair[1].ele_rise);
+        }
+        for max_elevation in frontier.iter().map(|result| result.ele_rise + 0.5) {
+            let optimum = frontier.iter().find(|result| result.ele_rise <= max_elevation).unwrap();
+            let exact = graph.find_exact_path(waypoints, criteria, true, Some(max_elevation), None, false, LABEL_LIMIT).unwrap();
+            assert!((exact.paths[0].distance - optimum.distance).abs() < 1e-9, "{} != {}", exact.paths[0].distance, optimum.distance);
+            assert_eq!(exact.paths[0].path[0], waypoints[0]);
+            let larac = graph.find_optimal_path(waypoints, criteria, true, Some(max_elevation), None, true).unwrap();
+            assert!(larac.paths.iter().all(|result| result.ele_rise <= max_elevation));
+            assert!(larac.lower_bound.unwrap() <= optimum.distance + 1e-9);
+        }
+    }
+    assert!(graph.find_optimal_path(&[3], criteria, true, None, None, false).is_err());
+}
+
+#[test]
+fn round_trip_test() {
+    let (latitude, longitude) = roundtrip::get_destination(48.7, 9.1, 90.0, 1.0);
+    assert!((calc_distance(48.7, 9.1, latitude as f32, longitude as f32) - 1.0).abs() < 1e-3);
+    assert!((latitude - 48.7).abs() < 1e-4 && longitude > 9.1);
+
+    let graph = build_grid_graph(30);
+    let start = 15 * 30 + 15;
+    let round_trips = roundtrip::find_round_trips(&graph, start, Criteria::new(TravelType::Bicycle), 4.0, Some(150.0), 3).unwrap();
+    assert!(!round_trips.is_empty() && round_trips.len() <= 3);
+    for round_trip in round_trips.iter() {
+        let path = &round_trip.route.path;
+        assert_eq!((path[0], *path.last().unwrap()), (start, start));
+        assert!((round_trip.length - 4.0).abs() < 1.2, "length {}", round_trip.length);
+        assert!(round_trip.reused_share <= 0.2);
+        assert!(round_trip.route.ele_rise <= 150.0);
+        let (length, _) = graph.get_path_length_and_duration(path, &Criteria::new(TravelType::Bicycle), true);
+        assert!((length - round_trip.length).abs() < 1e-9);
+    }
+    for pair in round_trips.windows(2) {
+        assert!(pair[0].route.path != pair[1].route.path);
+    }
+    assert!(roundtrip::find_round_trips(&graph, start, Criteria::new(TravelType::Bicycle), 0.0, None, 3).is_err());
+    let error = roundtrip::find_round_trips(&graph, start, Criteria::new(TravelType::Bicycle), 4.0, None, 0).err().unwrap();
+    assert_eq!(get_status(&error), StatusCode::BAD_REQUEST);
+    let error = roundtrip::find_round_trips(&graph, start, Criteria::new(TravelType::Bicycle), 4.0, None, roundtrip::DIRECTIONS + 1).err().unwrap();
+    assert_eq!(get_status(&error), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // avoided ways cost four times as much, the path over node 1 (1 km) is still shorter than the one over node 2 (6 km)
+    let detours = build_detour_graph();
+    let avoided_ways: HashSet<(usize, usize)> = [(0, 1), (1, 4)].iter().cloned().collect();
+    let avoiding = Criteria {
+        avoided_ways: Some(&avoided_ways),
+        ..Criteria::new(TravelType::Bicycle)
+    };
+    let route = detours.find_optimal_path(&[0, 4], avoiding, true, None, None, false).unwrap().paths.remove(0);
+    assert_eq!((route.path, route.distance), (vec![0, 1, 4], 4.0));
+}
+
+#[test]
+fn reachable_test() {
+    let graph = build_bumpy_grid_graph(12);
+    for &travel_type in TravelType::ALL.iter() {
+        for &(use_distance, max_cost) in &[(true, 1.5), (false, 0.1)] {
+            let criteria = Criteria::new(travel_type);
+            for &max_elevation in &[None, Some(20.0)] {
+                let reachable = graph.find_reachable(5, criteria, use_distance, max_cost, max_elevation, LABEL_LIMIT).unwrap();
+                let mut costs = vec![None; 144];
+                for node in reachable.iter() {
+                    assert!(costs[node.node].is_none(), "node {} reached twice", node.node);
+                    costs[node.node] = Some(node.cost);
+                    assert!(node.ele_rise <= max_elevation.unwrap_or(f64::MAX));
+                }
+                // the cheapest path within the elevation limit is the first of the pareto frontier
+                for (end, &cost) in costs.iter().enumerate() {
+                    let optimum = graph
+                        .pareto_search(&[5, end], criteria, use_distance, (max_elevation, None), LABEL_LIMIT)
+                        .unwrap()
+                        .first()
+                        .map(|path| path.distance)
+                        .filter(|&distance| distance <= max_cost);
+                    match (cost, optimum) {
+                        (Some(cost), Some(optimum)) => assert!((cost - optimum).abs() < 1e-9, "{} != {}", cost, optimum),
+                        (None, None) => {}
+                        _ => panic!("node {} reachable in only one search", end),
+                    }
+                }
+            }
+        }
+    }
+    assert!(graph.find_reachable(5, Criteria::new(TravelType::Bicycle), true, 100.0, Some(20.0), 10).is_err());
+}
+
+#[test]
+fn isochrone_test() {
+    let hull = isochrone::get_convex_hull(vec![(0.0, 0.0), (2.0, 0.0), (1.0, 1.0), (2.0, 2.0), (0.0, 2.0), (1.0, 0.0), (0.0, 0.0)]);
+    assert_eq!(hull, vec![(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]);
+    assert_eq!(isochrone::get_convex_hull(vec![(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]), vec![(0.0, 0.0), (2.0, 2.0)]);
+
+    let graph = build_grid_graph(30);
+    let criteria = Criteria::new(TravelType::Bicycle);
+    let start = 15 * 30 + 15;
+    let isochrones = isochrone::find_isochrones(&graph, start, criteria, true, &[2.0, 0.5, 1.0], None).unwrap();
+    assert_eq!(isochrones.iter().map(|isochrone| isochrone.budget).collect::<Vec<f64>>(), vec![2.0, 0.5, 1.0]);
+    assert!(isochrones[1].reachable_nodes < isochrones[2].reachable_nodes && isochrones[2].reachable_nodes < isochrones[0].reachable_nodes);
+    let reachable = graph.find_reachable(start, criteria, true, 2.0, None, LABEL_LIMIT).unwrap();
+    assert_eq!(reachable.len(), isochrones[0].reachable_nodes);
+    // every reachable node is inside the hull, left of all its counterclockwise edges
+    let hull = &isochrones[0].hull;
+    for node in reachable.iter() {
+        let node = graph.get_node(node.node);
+        let (x, y) = (node.longitude as f64, node.latitude as f64);
+        for i in 0..hull.len() {
+            let (a, b) = (hull[i], hull[(i + 1) % hull.len()]);
+            assert!((b.0 - a.0) * (y - a.1) - (b.1 - a.1) * (x - a.0) >= -1e-12);
+        }
+    }
+    // an elevation limit makes the area smaller
+    let limited = isochrone::find_isochrones(&graph, start, criteria, true, &[2.0], Some(10.0)).unwrap();
+    assert!(limited[0].reachable_nodes < isochrones[0].reachable_nodes);
+    assert!(limited[0].max_ele_rise <= 10.0 && isochrones[0].max_ele_rise > 10.0);
+    assert!(isochrone::find_isochrones(&graph, start, criteria, true, &[], None).is_err());
+    assert!(isochrone::find_isochrones(&graph, start, criteria, true, &[1.0, -1.0], None).is_err());
+}
+
+#[test]
+fn matrix_test() {
+    let graph = build_grid_graph(30);
+    let sources = [0, 437, 899, 0];
+    let targets = [899, 12, 437, 5, 5];
+    for &travel_type in TravelType::ALL.iter() {
+        let criteria = Criteria::new(travel_type);
+        for &use_distance in &[true, false] {
+            let matrix = matrix::find_matrix(&graph, &sources, &targets, criteria, use_distance, 20).unwrap();
+            for (row, &source) in sources.iter().enumerate() {
+                for (column, &target) in targets.iter().enumerate() {
+                    let optimum = graph.dijkstra(Dijkstra::Multiplier, source, target, criteria, use_distance, Some(0.0)).unwrap();
+                    // paths of the same costs may differ in the other values
+                    let cost = if use_distance { matrix.distances[row][column] } else { matrix.durations[row][column] };
+                    assert!((cost.unwrap() - optimum.distance).abs() < 1e-9);
+                }
+            }
+            // the second row of source 0 is copied
+            assert_eq!(matrix.ascents[0], matrix.ascents[3]);
+        }
+    }
+    assert!(matrix::find_matrix(&graph, &sources, &targets, Criteria::new(TravelType::Bicycle), true, 19).is_err());
+    assert!(matrix::find_matrix(&graph, &[], &targets, Criteria::new(TravelType::Bicycle), true, 20).is_err());
+    assert_eq!(matrix::check_size(4, 5, 20), Ok(()));
+    let error = matrix::check_size(usize::MAX, 2, 20).unwrap_err();
+    assert_eq!(get_status(&error), StatusCode::PAYLOAD_TOO_LARGE);
+    // cars can not use the footways of the ring
+    let graph = build_graph(&[(48.0, 9.0), (48.001, 9.0), (48.001, 9.001)], 4);
+    let matrix = matrix::find_matrix(&graph, &[0], &[0, 1], Criteria::new(TravelType::Car), true, 20).unwrap();
+    assert_eq!(matrix.distances, vec![vec![Some(0.0), None]]);
+    assert_eq!(matrix.ascents, vec![vec![Some(0.0), None]]);
+}
+
+#[test]
+fn gpx_test() {
+    let path = [
+        Node {
+            latitude: 48.7,
+            longitude: 9.1,
+            elevation: 250.0,
+        },
+        Node {
+            latitude: 48.701,
+            longitude: 9.1015,
+            elevation: 262.5,
+        },
+    ];
+    let tracks = [gpx::Track {
+        name: "Alb & back".to_string(),
+        description: "12.35 km, 1 h 05 min, 230 m ascent".to_string(),
+        path: &path,
+    }];
+    let gpx = gpx::write_gpx("<Alb>", "12.35 km, 1 h 05 min, 230 m ascent", &tracks);
+    assert!(gpx.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gpx version=\"1.1\""));
+    assert!(gpx.contains("<metadata>\n    <name>&lt;Alb&gt;</name>\n    <desc>12.35 km, 1 h 05 min, 230 m ascent</desc>"));
+    assert!(gpx.contains("<trk>\n    <name>Alb &amp; back</name>"));
+    assert!(gpx.contains("<trkpt lat=\"48.7\" lon=\"9.1\"><ele>250</ele></trkpt>"));
+    assert!(gpx.contains("<trkpt lat=\"48.701\" lon=\"9.1015\"><ele>262.5</ele></trkpt>"));
+    assert_eq!(gpx.matches("<trkpt").count(), 2);
+    assert!(gpx.ends_with("</trkseg>\n  </trk>\n</gpx>\n"));
+}
+
+#[test]
+fn geojson_test() {
+    assert_eq!(get_format(Some("geojson"), Some("application/json")), Ok(Format::GeoJson));
+    assert_eq!(get_format(Some("json"), Some("application/geo+json")), Ok(Format::Json));
+    assert_eq!(get_format(None, Some("text/html;q=0.9, application/gpx+xml;q=0.8, application/geo+json")), Ok(Format::Gpx));
+    assert_eq!(get_format(None, Some("application/geo+json")), Ok(Format::GeoJson));
+    assert_eq!(get_format(None, Some("*/*")), Ok(Format::Json));
+    assert_eq!(get_format(None, None), Ok(Format::Json));
+    let error = get_format(Some("kml"), Some("application/json")).unwrap_err();
+    assert_eq!(get_status(&error), StatusCode::BAD_REQUEST);
+    assert!(error.to_string().contains("json, geojson or gpx"));
+
+    let graph = build_detour_graph();
+    let criteria = Criteria::new(TravelType::Bicycle);
+    let routes = graph.find_optimal_path(&[0, 4], criteria, true, Some(8.0), None, false).unwrap();
+    let multiplier = routes.paths[0].multiplier;
+    let lower_bound = routes.lower_bound;
+    let responses: Vec<Response> = routes.paths.into_iter().map(|dr| get_response(&graph, dr, &[0, 4], &criteria, true, lower_bound, true)).collect();
+    assert_eq!(responses[0].lower_bound_m, lower_bound.map(|bound| bound * 1000.0));
+    assert_eq!(responses[0].lower_bound_s, None);
+    let geojson = get_geojson(&responses);
+    assert_eq!(geojson["type"], "FeatureCollection");
+    let feature = &geojson["features"][0];
+    assert_eq!(feature["geometry"]["type"], "LineString");
+    let coordinates = feature["geometry"]["coordinates"].as_array().unwrap();
+    assert_eq!(coordinates.len(), responses[0].path.len());
+    for (coordinate, node) in coordinates.iter().zip(responses[0].path.iter()) {
+        let coordinate: Vec<f64> = coordinate.as_array().unwrap().iter().map(|value| value.as_f64().unwrap()).collect();
+        assert_eq!(coordinate, vec![node.longitude as f64, node.latitude as f64, node.elevation as f64]);
+    }
+    let properties = &feature["properties"];
+    assert_eq!(properties["distance_m"], responses[0].distance_m);
+    assert_eq!(properties["duration_s"], responses[0].duration_s);
+    assert_eq!(properties["ascent"], responses[0].statistics.ascent);
+    assert_eq!(properties["multiplier"].as_f64(), multiplier);
+}
+
+#[test]
+fn travel_type_test() {
+    assert_eq!(get_travel_type("bicycle"), Ok(TravelType::Bicycle));
+    let error = get_travel_type("horse").unwrap_err();
+    assert_eq!(get_status(&error), StatusCode::BAD_REQUEST);
+    assert!(get_rider(Some(0.0), None, false).is_err());
+    assert_eq!(get_rider(None, Some(70.0), false).unwrap().power, Rider::DEFAULT.power);
+    assert!(get_rider(None, Some(70.0), true).is_err());
+    assert!(get_rider(Some(120.0), None, true).is_err());
+    assert_eq!(get_rider(None, None, true).unwrap(), Rider::DEFAULT);
+    assert_eq!(check_limits(&[("max_descent", Some(0.0)), ("max_gradient_percent", None)]), Ok(()));
+    for &limit in &[-1.0, f64::NAN, f64::INFINITY] {
+        let error = check_limits(&[("max_descent", None), ("max_segment_climb", Some(limit))]).unwrap_err();
+        assert_eq!(get_status(&error), StatusCode::BAD_REQUEST);
+    }
+
+    // pedestrians snap to the same ways they may search on, including shared paths with bicycles
+    let graph = build_graph(&[(48.7758, 9.1829), (48.7760, 9.1840)], 3);
+    assert_eq!(graph.get_point_id(48.7758, 9.1830, &Criteria::new(TravelType::Foot)), Some(0));
+    assert!(graph.shortest_path(0, 1, Criteria::new(TravelType::Foot), true).is_some());
+    assert_eq!(graph.get_point_id(48.7758, 9.1830, &Criteria::new(TravelType::Car)), None);
+}
+
+#[test]
+fn profile_test() {
+    let profiles = profile::parse_profiles(include_str!("../profiles.toml")).unwrap();
+    let names: Vec<&str> = profiles.iter().map(|profile| profile.name.as_str()).collect();
+    assert_eq!(names, vec!["e_bike", "road_bike", "trekking", "mtb", "wheelchair", "pedestrian"]);
+    // pedestrians may not use motorways, names have to be unique
+    assert!(profile::parse_profiles("[[profile]]\nname = \"walk\"\ntravel_type = \"foot\"\nspeeds = { car_only = 5.0 }\n").is_err());
+    let climber = "[[profile]]\nname = \"climber\"\ntravel_type = \"bicycle\"\nspeeds = { all = 20.0 }\nsurfaces = { unpaved = 0.0 }\nuphill = [[0.0, 1.0], [10.0, 0.5]]\n";
+    assert!(profile::parse_profiles(&format!("{}{}", climber, climber)).is_err());
+
+    let climber = &profile::parse_profiles(climber).unwrap()[0];
+    let way = Way {
+        source: 0,
+        target: 1,
+        speed: 30,
+        distance: 1.0,
+        travel_type: 5,
+        surface: 0,
+    };
+    assert_eq!(climber.get_speed(&way, 0.0), 20.0);
+    assert_eq!(climber.get_speed(&way, 5.0), 15.0);
+    assert_eq!(climber.get_speed(&way, 20.0), 10.0);
+    assert_eq!(climber.get_speed(&way, -5.0), 20.0);
+    assert_eq!(climber.get_speed(&Way { speed: 10, ..way }, 0.0), 10.0);
+    assert!(!climber.may_use(&Way { surface: Surface::Unpaved as u32, ..way }));
+    assert!(!climber.may_use(&Way { travel_type: 2, ..way }));
+
+    // 0 -> 1 rises 10 m on 0.5 km (2 %, 18 km/h), 1 -> 4 descends 10 m (20 km/h)
+    let graph = build_detour_graph();
+    let criteria = Criteria::with_profile(climber);
+    let (_, duration) = graph.get_path_length_and_duration(&[0, 1, 4], &criteria, false);
+    assert!((duration - (0.5 / 18.0 + 0.5 / 20.0)).abs() < 1e-9);
+    let dijkstra = graph.dijkstra(Dijkstra::Multiplier, 0, 4, criteria, false, Some(0.0)).unwrap();
+    let a_star = graph.a_star(Dijkstra::Multiplier, 0, 4, criteria, false, Some(0.0)).unwrap();
+    assert_eq!(dijkstra.path, vec![0, 1, 4]);
+    assert!((a_star.distance - dijkstra.distance).abs() < 1e-9);
+
+    // road bikes do not use tracks, so they are not snapped to them
+    let tracks = build_graph(&[(48.7758, 9.1829), (48.7760, 9.1840)], 3);
+    let road_bike = profiles.iter().find(|profile| profile.name == "road_bike").unwrap();
+    let trekking = profiles.iter().find(|profile| profile.name == "trekking").unwrap();
+    assert_eq!(tracks.get_point_id(48.7758, 9.1830, &Criteria::with_profile(road_bike)), None);
+    assert_eq!(tracks.get_point_id(48.7758, 9.1830, &Criteria::with_profile(trekking)), Some(0));
+    assert!(get_criteria("", Some("unicycle"), &profiles).is_err());
+    assert_eq!(get_criteria("", Some("trekking"), &profiles).unwrap().travel_type, TravelType::Bicycle);
+    assert_eq!(get_criteria("bicycle", Some("trekking"), &profiles).unwrap().travel_type, TravelType::Bicycle);
+    let error = get_criteria("foot", Some("trekking"), &profiles).unwrap_err();
+    assert_eq!(get_status(&error), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn routing_error_test() {
+    // madrid is too far away from buenos aires and cars can not use the footways
+    let graph = build_graph(&[(-34.6037, -58.3816), (-34.6090, -58.3900)], 4);
+    assert_eq!(graph.get_point_id(40.4168, -3.7038, &Criteria::new(TravelType::Foot)), None);
+    assert_eq!(graph.get_point_id(-34.6040, -58.3820, &Criteria::new(TravelType::Car)), None);
+    let points = [Position {
+        latitude: -34.6040,
+        longitude: -58.3820,
+    }];
+    assert_eq!(snap(&graph, points.iter(), &Criteria::new(TravelType::Foot)), Ok(vec![0]));
+    let error = snap(&graph, points.iter(), &Criteria::new(TravelType::Car)).unwrap_err();
+    assert_eq!(error, RoutingError::NotSnapped {
+        latitude: -34.6040,
+        longitude: -58.3820,
+    });
+    assert_eq!(get_status(&error), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let graph = build_detour_graph();
+    let criteria = Criteria::new(TravelType::Bicycle);
+    // the path over node 3 rises and descends 1 m
+    let infeasible = RoutingError::ElevationInfeasible {
+        min_ele_rise: 1.0,
+        min_descent: 1.0,
+    };
+    assert_eq!(graph.find_optimal_path(&[0, 4], criteria, true, Some(0.5), None, false).err(), Some(infeasible.clone()));
+    assert_eq!(graph.find_pareto_paths(&[0, 4], criteria, true, None, Some(0.5), false).err(), Some(infeasible.clone()));
+    assert_eq!(get_status(&infeasible), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = serde_json::to_value(ErrorResponse {
+        message: infeasible.to_string(),
+        error: infeasible,
+    })
+    .unwrap();
+    assert_eq!(body["code"], "elevation_infeasible");
+    assert_eq!((body["min_ele_rise"].as_f64(), body["min_descent"].as_f64()), (Some(1.0), Some(1.0)));
+    assert!(body["message"].as_str().unwrap().contains("1 m"));
+    // ways only lead to node 4
+    assert_eq!(graph.find_optimal_path(&[4, 0], criteria, true, None, None, false).err(), Some(RoutingError::NoPath));
+    assert_eq!(graph.find_pareto_paths(&[4, 0], criteria, true, Some(5.0), None, false).err(), Some(RoutingError::NoPath));
+    assert_eq!(get_status(&RoutingError::NoPath), StatusCode::NOT_FOUND);
+    let error = graph.find_optimal_path(&[4], criteria, true, None, None, false).err().unwrap();
+    assert_eq!(serde_json::to_value(&error).unwrap()["code"], "invalid_request");
+    assert_eq!(get_status(&error), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn json_error_test() {
+    let overflow = get_json_error(JsonPayloadError::Overflow, 1024).as_response_error().error_response();
+    assert_eq!(overflow.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let content_type = get_json_error(JsonPayloadError::ContentType, 1024).as_response_error().error_response();
+    assert_eq!(content_type.status(), StatusCode::BAD_REQUEST);
+    let error = RoutingError::BodyTooLarge { limit: 1024 };
+    assert_eq!(serde_json::to_value(&error).unwrap()["code"], "body_too_large");
+    let wrong_type = serde_json::from_str::<Query>(r#"{"start": "Freiburg"}"#).err().unwrap();
+    let deserialize = get_json_error(JsonPayloadError::Deserialize(wrong_type), 1024).as_response_error().error_response();
+    assert_eq!(deserialize.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(deserialize.headers().get(header::CONTENT_TYPE).unwrap(), "application/json");
+}
+
+/// run with `cargo test --release -p osm-dijkstra-web -- --ignored --nocapture`
+#[test]
+#[ignore]
+fn search_benchmark() {
+    let mut map_data = build_grid_map_data(400);
+    for &by_distance in &[true, false] {
+        let timing = Instant::now();
+        let ch = ContractionHierarchy::new(&map_data, TravelType::Bicycle, by_distance);
+        println!("contraction (by distance: {}): {:?}", by_distance, timing.elapsed());
+        map_data.hierarchies.push(ch);
+    }
+    let graph = Graph::new(map_data);
+    let (start, end) = (400 * 180 + 20, 400 * 220 + 380);
+    for &use_distance in &[true, false] {
+        let timing = Instant::now();
+        let ch = graph.shortest_path(start, end, Criteria::new(TravelType::Bicycle), use_distance);
+        let ch_time = timing.elapsed();
+        let timing = Instant::now();
+        let dijkstra = graph.dijkstra(Dijkstra::Multiplier, start, end, Criteria::new(TravelType::Bicycle), use_distance, Some(0.0));
+        println!("shortest path (by distance: {}): dijkstra {:?}, contraction hierarchy {:?}", use_distance, timing.elapsed(), ch_time);
+        assert_same_costs(ch, dijkstra, Some(0.0));
+    }
+    for &(name, min_of, multiplier) in &[("distance", 0, 0.0), ("elevation", 1, f64::MAX), ("multiplier", 0, 0.05)] {
+        let mode = || if min_of == 0 { Dijkstra::Multiplier } else { Dijkstra::Elevation };
+        for &use_distance in &[true, false] {
+            let timing = Instant::now();
+            let dijkstra = graph.dijkstra(mode(), start, end, Criteria::new(TravelType::Bicycle), use_distance, Some(multiplier));
+            let dijkstra_time = timing.elapsed();
+            let timing = Instant::now();
+            let a_star = graph.a_star(mode(), start, end, Criteria::new(TravelType::Bicycle), use_distance, Some(multiplier));
+            let a_star_time = timing.elapsed();
+            let timing = Instant::now();
+            let bidirectional = graph.bidirectional_dijkstra(mode(), start, end, Criteria::new(TravelType::Bicycle), use_distance, Some(multiplier));
+            let bidirectional_time = timing.elapsed();
+            println!(
+                "{} (by distance: {}): dijkstra {:?}, a* {:?}, bidirectional {:?}",
+                name, use_distance, dijkstra_time, a_star_time, bidirectional_time
+            );
+            let cost_multiplier = if min_of == 0 { Some(multiplier) } else { None };
+            assert_same_costs(a_star, dijkstra.clone(), cost_multiplier);
+            assert_same_costs(bidirectional, dijkstra, cost_multiplier);
+        }
+    }
+    // the pareto frontier grows quickly with the length of the route, so a shorter one is used
+    let (start, end) = (400 * 190 + 170, 400 * 210 + 230);
+    for &use_distance in &[true, false] {
+        let timing = Instant::now();
+        let frontier = graph.pareto_search(&[start, end], Criteria::new(TravelType::Bicycle), use_distance, (None, None), LABEL_LIMIT).unwrap();
+        let pareto_time = timing.elapsed();
+        let max_elevation = frontier[frontier.len() / 2].ele_rise;
+        let timing = Instant::now();
+        let larac = graph.find_optimal_path(&[start, end], Criteria::new(TravelType::Bicycle), use_distance, Some(max_elevation), None, true).unwrap().paths;
+        println!(
+            "trade-offs (by distance: {}): pareto {} paths in {:?}, larac {} paths in {:?}",
+            use_distance, frontier.len(), pareto_time, larac.len(), timing.elapsed()
+        );
+        // in between two hull points, where LARAC can not find the optimum
+        let max_elevation = (frontier[frontier.len() / 2].ele_rise + frontier[frontier.len() / 2 + 1].ele_rise) / 2.0;
+        let timing = Instant::now();
+        let larac = graph.find_optimal_path(&[start, end], Criteria::new(TravelType::Bicycle), use_distance, Some(max_elevation), None, false).unwrap();
+        let larac_time = timing.elapsed();
+        let timing = Instant::now();
+        let exact = graph.find_exact_path(&[start, end], Criteria::new(TravelType::Bicycle), use_distance, Some(max_elevation), None, false, LABEL_LIMIT).unwrap();
+        println!(
+            "constrained (by distance: {}): larac {:.4} (lower bound {:.4}) in {:?}, exact {:.4} in {:?}",
+            use_distance, larac.paths[0].distance, larac.lower_bound.unwrap(), larac_time, exact.paths[0].distance, timing.elapsed()
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::BinaryHeap;
+use std::mem;
+
+/// leg labels kept after a search, enough for the two per leg of a route without via points
+pub const KEPT_LEG_LABELS: usize = 2;
+
+/// cost and predecessor of every node, invalidated in constant time
+///
+/// A label is only valid if its stamp matches the current stamp, so a reset just increments the stamp
+/// instead of touching every node of the graph.
+pub struct Labels {
+    labels: Vec<(f64, Option<usize>)>,
+    stamps: Vec<u32>,
+    stamp: u32,
+}
+
+impl Labels {
+    pub fn new() -> Labels {
+        return Labels {
+            labels: Vec::new(),
+            stamps: Vec::new(),
+            stamp: 0,
+        };
+    }
+
+    /// invalidates all labels, the arrays grow if the graph has more nodes than the previous one
+    pub fn reset(&mut self, amount_nodes: usize) {
+        if self.labels.len() < amount_nodes {
+            self.labels.resize(amount_nodes, (f64::MAX, None));
+            self.stamps.resize(amount_nodes, 0);
+        }
+        if self.stamp == u32::MAX {
+            // old stamps would become valid again after the wrap around
+            for stamp in self.stamps.iter_mut() {
+                *stamp = 0;
+            }
+            self.stamp = 0;
+        }
+        self.stamp += 1;
+    }
+
+    /// returns cost and predecessor, nodes without label have infinite costs
+    pub fn get(&self, node: usize) -> (f64, Option<usize>) {
+        return if self.stamps[node] == self.stamp {
+            self.labels[node]
+        } else {
+            (f64::MAX, None)
+        };
+    }
+
+    pub fn cost(&self, node: usize) -> f64 {
+        return self.get(node).0;
+    }
+
+    pub fn set(&mut self, node: usize, cost: f64, previous: Option<usize>) {
+        self.labels[node] = (cost, previous);
+        self.stamps[node] = self.stamp;
+    }
+
+    pub fn byte_size(&self) -> usize {
+        return self.labels.capacity() * mem::size_of::<(f64, Option<usize>)>() + self.stamps.capacity() * mem::size_of::<u32>();
+    }
+}
+
+/// labels and heaps of a forward and a backward search, kept between searches to avoid allocations
+pub struct SearchWorkspace<T: Ord> {
+    pub forward: Labels,
+    pub backward: Labels,
+    pub forward_heap: BinaryHeap<T>,
+    pub backward_heap: BinaryHeap<T>,
+    /// labels of searches that need two per leg of a route, at most KEPT_LEG_LABELS are kept after a search
+    pub leg_labels: Vec<Labels>,
+}
+
+impl<T: Ord> SearchWorkspace<T> {
+    pub fn new() -> SearchWorkspace<T> {
+        return SearchWorkspace {
+            forward: Labels::new(),
+            backward: Labels::new(),
+            forward_heap: BinaryHeap::new(),
+            backward_heap: BinaryHeap::new(),
+            leg_labels: Vec::new(),
+        };
+    }
+
+    /// prepares a search on a graph with the given amount of nodes, the heaps keep their capacity
+    pub fn reset(&mut self, amount_nodes: usize) {
+        self.forward.reset(amount_nodes);
+        self.backward.reset(amount_nodes);
+        self.forward_heap.clear();
+        self.backward_heap.clear();
+        for labels in self.leg_labels.iter_mut() {
+            labels.reset(amount_nodes);
+        }
+    }
+
+    /// makes sure there are at least amount leg labels, new ones are reset like the others
+    pub fn add_leg_labels(&mut self, amount: usize, amount_nodes: usize) {
+        while self.leg_labels.len() < amount {
+            let mut labels = Labels::new();
+            labels.reset(amount_nodes);
+            self.leg_labels.push(labels);
+        }
+    }
+
+    /// frees the leg labels beyond KEPT_LEG_LABELS, so a route with many via points does not pin them for the life of the thread
+    pub fn release_leg_labels(&mut self) {
+        self.leg_labels.truncate(KEPT_LEG_LABELS);
+        self.leg_labels.shrink_to_fit();
+    }
+
+    /// memory used by labels and heaps in bytes
+    pub fn byte_size(&self) -> usize {
+        return self.forward.byte_size()
+            + self.backward.byte_size()
+            + self.leg_labels.iter().map(|labels| labels.byte_size()).sum::<usize>()
+            + (self.forward_heap.capacity() + self.backward_heap.capacity()) * mem::size_of::<T>();
+    }
+}
@@ -1,39 +1,60 @@
-var map = L.map('map', {
-    maxBounds: [
-        [47.3, 5.9], // Southwest coordinates
-        [54.9, 16.9512215] // Northeast coordinates
-    ],
-}).setView([51.1657, 10.4515], 6);
+// graphs of any region can be loaded, so the map is not bounded
+var map = L.map('map').setView([51.1657, 10.4515], 6);
 L.tileLayer('https://api.tiles.mapbox.com/v4/{id}/{z}/{x}/{y}.png?access_token={accessToken}', {
     attribution: 'Map data &copy; <a href="https://www.openstreetmap.org/">OpenStreetMap</a> contributors, <a href="https://creativecommons.org/licenses/by-sa/2.0/">CC-BY-SA</a>, Imagery © <a href="https://www.mapbox.com/">Mapbox</a>',
     maxZoom: 18,
-    minZoom: 6,
+    minZoom: 2,
     id: 'mapbox.streets',
     accessToken: 'pk.eyJ1IjoibWFwYm94IiwiYSI6ImNpejY4NXVycTA2emYycXBndHRqcmZ3N3gifQ.rJcFIG214AriISLbB6B5aw'
 }).addTo(map);
 
 map.on('click', onMapClick);
 
-let url = "http://localhost:8080/";
+// relative to the page, so the frontend also works behind a reverse proxy
+let url = "";
 
 let startPoint;
 let startMarker;
 let endPoint;
+// end of the displayed routes, the start for round trips
+let routeEnd;
 let endMarker;
+// passed in order between start and end
+let viaPoints = [];
+let viaMarkers = [];
 let tmpMarker;
 let pathsOnMap = [];
 let result;
+// body of the last route request, sent again for the GPX download
+let lastQuery;
 let lineChart;
 let scatterChart;
 let xhr = new XMLHttpRequest();
 
+loadProfiles();
+
 function onMapClick(e) {
     if (tmpMarker) {
         map.removeLayer(tmpMarker);
     }
     tmpMarker = L.marker(e.latlng).addTo(map);
     tmpMarker.setLatLng(e.latlng);
-    tmpMarker.bindPopup("<button class='set-point set-start' onclick='setStart()''>Set Start</button><button class='set-point set-end' onclick='setEnd()''>Set End</button>").openPopup();
+    tmpMarker.bindPopup("<button class='set-point set-start' onclick='setStart()''>Set Start</button><button class='set-point set-end' onclick='setEnd()''>Set End</button><button class='set-point' onclick='addVia()''>Add Via</button>").openPopup();
+}
+
+function addVia() {
+    let coords = tmpMarker.getLatLng();
+    let viaMarker = L.marker(coords).addTo(map);
+    viaMarker.bindTooltip("via " + (viaPoints.length + 1) + ", click to remove");
+    viaMarker.on('click', function () {
+        let index = viaMarkers.indexOf(viaMarker);
+        viaPoints.splice(index, 1);
+        viaMarkers.splice(index, 1);
+        map.removeLayer(viaMarker);
+    });
+    viaPoints.push(coords);
+    viaMarkers.push(viaMarker);
+    map.removeLayer(tmpMarker);
 }
 
 function setStart() {
@@ -78,18 +99,17 @@ function setEnd() {
     }
 }
 
-function showElevationGraph(elevationPoints, totalElevation, color) {
-    console.log(elevationPoints);
+function showElevationGraph(profile, totalElevation, color) {
     let result = document.getElementById('totalElevation');
     result.innerText = 'Total: ' + totalElevation.toFixed(2) + 'm';
     let graph = document.getElementById('elevationGraph').getContext('2d');
     lineChart = new Chart(graph, {
         type: 'line',
         data: {
-            labels: elevationPoints.map((ele, index) => index),
+            labels: profile.map(point => point.distance.toFixed(2)),
             datasets: [{
                 label: 'Elevation in m',
-                data: elevationPoints,
+                data: profile.map(point => point.elevation),
                 backgroundColor: color,
                 borderColor: color,
                 fill: false,
@@ -143,13 +163,31 @@ function removeGraphsAndEdges() {
     pathsOnMap = []
 }
 
+// fills the profile selection with the profiles the server loaded
+function loadProfiles() {
+    let xhr = new XMLHttpRequest();
+    xhr.open("GET", url + "profiles", true);
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState === 4 && xhr.status === 200) {
+            let select = document.getElementById("profile");
+            for (let profile of JSON.parse(xhr.responseText)) {
+                let option = document.createElement("option");
+                option.value = profile.name;
+                option.text = profile.name + " (" + profile.travel_type + ")";
+                select.add(option);
+            }
+        }
+    };
+    xhr.send();
+}
+
 function query() {
     hideResult();
     hideInvalidRequest();
     hideNoPathFound();
     hideSelectStartAndEnd();
     removeGraphsAndEdges();
-    result = new Result();
+    result = new Result(document.getElementById("optimization").value === "distance");
 
     if (pathsOnMap.length > 1) {
         for (let path of pathsOnMap) {
@@ -163,6 +201,7 @@ function query() {
         return;
     }
 
+    routeEnd = endPoint;
     let xhr = new XMLHttpRequest();
     xhr.open("POST", url + "dijkstra", true);
     xhr.setRequestHeader("Content-type", "application/json;charset=UTF-8");
@@ -173,40 +212,170 @@ function query() {
         if (xhr.readyState === 4 && xhr.status === 200) {
             displayResult(xhr.responseText)
         } else if (xhr.readyState === 4) {
-            showInvalidRequest();
+            showRequestError(xhr.responseText);
         }
     };
 
     let travelType = document.getElementById("travel-type").value;
+    let profile = document.getElementById("profile").value;
     let optimization = document.getElementById("optimization").value === "distance";
     let maxElevation = parseInt(document.getElementById("max-elevation").value);
+    let maxDescent = parseFloat(document.getElementById("max-descent").value);
+    let maxGradient = parseFloat(document.getElementById("max-gradient").value);
+    let maxSegmentClimb = parseFloat(document.getElementById("max-segment-climb").value);
+    let riderPower = parseFloat(document.getElementById("rider-power").value);
+    let riderMass = parseFloat(document.getElementById("rider-mass").value);
     let allPaths = document.getElementById("all-paths").checked
+    let algorithm = document.getElementById("algorithm").value;
     let body = {
         "start": {
             "latitude": startPoint.lat,
             "longitude": startPoint.lng
         },
+        "via": viaPoints.map(point => ({
+            "latitude": point.lat,
+            "longitude": point.lng
+        })),
         "end": {
             "latitude": endPoint.lat,
             "longitude": endPoint.lng
         },
-        "travel_type": travelType,
+        // profiles have their own travel type, undefined fields are left out
+        "travel_type": profile === "" ? travelType : undefined,
+        "profile": profile === "" ? null : profile,
         "by_distance": optimization,
-        "max_ele_rise": maxElevation,
-        "all_paths": allPaths
+        "max_ele_rise": isNaN(maxElevation) ? null : maxElevation,
+        "max_descent": isNaN(maxDescent) ? null : maxDescent,
+        "max_gradient_percent": isNaN(maxGradient) ? null : maxGradient,
+        "max_segment_climb": isNaN(maxSegmentClimb) ? null : maxSegmentClimb,
+        "rider_power": isNaN(riderPower) ? null : riderPower,
+        "rider_mass": isNaN(riderMass) ? null : riderMass,
+        "all_paths": allPaths,
+        "algorithm": algorithm
     };
+    lastQuery = body;
     let data = JSON.stringify(body);
     // console.log("request: " + data);
     xhr.send(data);
 }
 
+function downloadGpx() {
+    if (typeof lastQuery === 'undefined') {
+        showSelectStartAndEnd();
+        return;
+    }
+    let xhr = new XMLHttpRequest();
+    xhr.open("POST", url + "dijkstra", true);
+    xhr.setRequestHeader("Content-type", "application/json;charset=UTF-8");
+    xhr.responseType = "blob";
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState === 4 && xhr.status === 200) {
+            let link = document.createElement("a");
+            link.href = URL.createObjectURL(xhr.response);
+            link.download = "route.gpx";
+            link.click();
+            setTimeout(() => URL.revokeObjectURL(link.href), 1000);
+        } else if (xhr.readyState === 4 && xhr.response) {
+            // errors are JSON like the answers to query(), but read as blob like the GPX file
+            xhr.response.text().then(showRequestError);
+        } else if (xhr.readyState === 4) {
+            showInvalidRequest();
+        }
+    };
+    xhr.send(JSON.stringify(Object.assign({}, lastQuery, {"format": "gpx"})));
+}
+
+function queryRoundTrip() {
+    hideResult();
+    hideInvalidRequest();
+    hideNoPathFound();
+    hideSelectStartAndEnd();
+    removeGraphsAndEdges();
+    result = new Result(true);
+
+    if (typeof startPoint === 'undefined') {
+        showSelectStartAndEnd();
+        return;
+    }
+    routeEnd = startPoint;
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("POST", url + "roundtrip", true);
+    xhr.setRequestHeader("Content-type", "application/json;charset=UTF-8");
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState === 4 && xhr.status === 200) {
+            displayResult(xhr.responseText)
+        } else if (xhr.readyState === 4) {
+            showRequestError(xhr.responseText);
+        }
+    };
+
+    let maxElevation = parseInt(document.getElementById("max-elevation").value);
+    let body = {
+        "start": {
+            "latitude": startPoint.lat,
+            "longitude": startPoint.lng
+        },
+        "length": parseFloat(document.getElementById("loop-length").value),
+        "travel_type": document.getElementById("travel-type").value,
+        "max_ele_rise": isNaN(maxElevation) ? null : maxElevation
+    };
+    xhr.send(JSON.stringify(body));
+}
+
+function queryIsochrone() {
+    hideResult();
+    hideInvalidRequest();
+    hideNoPathFound();
+    hideSelectStartAndEnd();
+    removeGraphsAndEdges();
+
+    if (typeof startPoint === 'undefined') {
+        showSelectStartAndEnd();
+        return;
+    }
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("POST", url + "isochrone", true);
+    xhr.setRequestHeader("Content-type", "application/json;charset=UTF-8");
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState === 4 && xhr.status === 200) {
+            let areas = JSON.parse(xhr.responseText);
+            if (areas.features.length === 0) {
+                showNoPathFound();
+                return;
+            }
+            let area = L.geoJSON(areas, {
+                style: {color: "#2b83ba", weight: 2, fillOpacity: 0.15}
+            }).addTo(map);
+            pathsOnMap.push(area);
+        } else if (xhr.readyState === 4) {
+            showRequestError(xhr.responseText);
+        }
+    };
+
+    let maxElevation = parseInt(document.getElementById("max-elevation").value);
+    let minutes = document.getElementById("reach-minutes").value.split(",").map(parseFloat).filter(minute => minute > 0);
+    let body = {
+        "start": {
+            "latitude": startPoint.lat,
+            "longitude": startPoint.lng
+        },
+        "travel_type": document.getElementById("travel-type").value,
+        "by_distance": false,
+        "budgets": minutes.map(minute => minute * 60),
+        "max_ele_rise": isNaN(maxElevation) ? null : maxElevation
+    };
+    xhr.send(JSON.stringify(body));
+}
+
 function createScatterChart(result) {
     let graph = document.getElementById('scatterGraph').getContext('2d');
     datasetArray = [];
     for (resultPath of result.weightedResults) {
         datasetArray.push({
             data: [{
-                x: resultPath.distance,
+                x: resultPath.cost,
                 y: resultPath.elevation,
             }],
             backgroundColor: resultPath.color,
@@ -234,7 +403,7 @@ function createScatterChart(result) {
                     scaleLabel: {
                         display: true,
 
-                        labelString: `distance in ${result.distanceType}`,
+                        labelString: result.costUnit === "km" ? 'distance in km' : 'duration in min',
                     },
 
                 }]
@@ -248,13 +417,32 @@ function createScatterChart(result) {
 function createViews(result) {
     createScatterChart(result);
     for (let resultPath of result.weightedResults) {
-        createPathView(resultPath.path, resultPath.distance, resultPath.distance_type, resultPath.elevation, resultPath.color);
+        createPathView(resultPath.path, resultPath.formatted, resultPath.elevation, resultPath.statistics, resultPath.color);
     }
 }
 
 
 function showInvalidRequest() {
-    document.getElementById("invalid-request").style.display = "block";
+    let toast = document.getElementById("invalid-request");
+    toast.textContent = "Invalid Request";
+    toast.style.display = "block";
+}
+
+// shows the message of an error response, e.g. the lowest possible elevation rise
+function showRequestError(responseText) {
+    let message;
+    try {
+        message = JSON.parse(responseText).message;
+    } catch (e) {
+        message = undefined;
+    }
+    if (!message) {
+        showInvalidRequest();
+        return;
+    }
+    let toast = document.getElementById("invalid-request");
+    toast.textContent = message;
+    toast.style.display = "block";
 }
 
 function hideInvalidRequest() {
@@ -288,7 +476,10 @@ function hideSelectStartAndEnd() {
 
 function showResultToast(result) {
     var tmp = document.getElementById("result");
-    tmp.innerHTML = `Shortest path for elevation restriction has ${result.minDistance} ${result.distanceType}`;
+    tmp.innerHTML = `Shortest path for elevation restriction: ${result.shortest.formatted}`;
+    if (result.lowerBound != null) {
+        tmp.innerHTML += `, no path within the limit is below ${result.lowerBound.toFixed(2)} ${result.costUnit}`;
+    }
     tmp.style.display = "block";
 }
 
@@ -316,7 +507,7 @@ var redIcon = new L.Icon({
     shadowSize: [41, 41]
 });
 
-function createPathView(path, distance, distanceType, elevation, color) {
+function createPathView(path, formatted, elevation, statistics, color) {
     // create [lat, lng] array for leaflet map
     let coords = path.map(node => [node.latitude, node.longitude]);
     let offTrackStart = L.polyline([startPoint, coords[0]], {
@@ -327,7 +518,7 @@ function createPathView(path, distance, distanceType, elevation, color) {
     edge.setStyle({
         color: color
     });
-    let offTrackEnd = L.polyline([coords[coords.length - 1], endPoint], {
+    let offTrackEnd = L.polyline([coords[coords.length - 1], routeEnd], {
         'dashArray': 10,
         'weight': 2
     });
@@ -335,13 +526,16 @@ function createPathView(path, distance, distanceType, elevation, color) {
 
     pathsOnMap.push(newPath);
     map.addLayer(newPath);
-    map.fitBounds([startPoint, endPoint]);
+    map.fitBounds(edge.getBounds().extend(startPoint).extend(routeEnd));
 
-    edge.bindPopup(`length: ${distance}${distanceType}\n` +
-        `elevation: ${elevation.toFixed(2)}'m'`);
+    edge.bindPopup(`${formatted}\n` +
+        `elevation: ${elevation.toFixed(2)}'m'\n` +
+        `descent: ${statistics.descent.toFixed(2)}m\n` +
+        `between ${statistics.min_elevation.toFixed(0)}m and ${statistics.max_elevation.toFixed(0)}m\n` +
+        `gradient: ${statistics.average_gradient_percent.toFixed(1)}% on average, ${statistics.max_gradient_percent.toFixed(1)}% at most`);
     edge.on('mouseover', function (e) {
         this.openPopup();
-        showElevationGraph(path.map(node => node.elevation), elevation, color);
+        showElevationGraph(statistics.profile, elevation, color);
     });
     edge.on('mouseout', function (e) {
         this.closePopup();
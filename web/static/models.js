@@ -8,16 +8,21 @@ function get_next_edge_color() {
 
 class Result {
     weightedResults = [];
-    _distanceType;
+    _byDistance;
+
+    constructor(byDistance) {
+        this._byDistance = byDistance;
+    }
 
     add(result) {
         result.color = get_next_edge_color();
+        // the costs the search minimized, in km or min
+        result.cost = this._byDistance ? result.distance_m / 1000 : result.duration_s / 60;
         this.weightedResults.push(result);
-        this._distanceType = result.distance_type;
     }
 
-    get distanceType() {
-        return this._distanceType
+    get costUnit() {
+        return this._byDistance ? "km" : "min"
     }
 
     get minElevation() {
@@ -28,11 +33,20 @@ class Result {
         return Math.max(...this.weightedResults.map(result => result.elevation))
     }
 
-    get minDistance() {
-        return Math.min(...this.weightedResults.map(result => result.distance))
+    get shortest() {
+        return this.weightedResults.reduce((best, result) => result.cost < best.cost ? result : best)
     }
 
-    get maxDistance() {
-        return Math.max(...this.weightedResults.map(result => result.distance))
+    get lowerBound() {
+        // the same for every path of a response, in m by distance and s by time
+        if (this.weightedResults.length === 0) {
+            return null
+        }
+        let result = this.weightedResults[0];
+        let lowerBound = this._byDistance ? result.lower_bound_m : result.lower_bound_s;
+        if (lowerBound == null) {
+            return null
+        }
+        return this._byDistance ? lowerBound / 1000 : lowerBound / 60
     }
 }
\ No newline at end of file